@@ -0,0 +1,262 @@
+//! Layered configuration
+//!
+//! A `Request` can also be put together from several partial sources, each
+//! of which may set any of its fields. A `Loader` reads them in order of
+//! increasing precedence, typically a defaults file, an environment file,
+//! `REQUEST_*` environment variables and command-line flags, and each field
+//! is taken from the last layer that set it. The defaults only apply to the
+//! fields no layer set, and `Sources` tells which layer supplied each field.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::de::DeserializeSeed;
+use serde_json::{Map, Number, Value};
+
+use super::{Defaults, PartialRequest, PartialRequestSeed, Request, TimeoutBounds};
+
+/// The prefix of the environment variables a `Loader` reads.
+const ENV_PREFIX: &str = "REQUEST_";
+
+/// The fields that a layer may set.
+const FIELDS: &[&str] = &["resource", "timeout", "priority"];
+
+/// The name of the layer that supplied a field, or `"default"`.
+#[derive(Debug)]
+pub struct Sources {
+    pub resource: String,
+    pub timeout: String,
+    pub priority: String,
+}
+
+impl fmt::Display for Sources {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            formatter,
+            "resource from {}, timeout from {}, priority from {}",
+            self.resource, self.timeout, self.priority
+        )
+    }
+}
+
+#[derive(Debug)]
+pub enum LoadError {
+    /// A file could not be read.
+    Io(String, io::Error),
+    /// A layer has a value of the wrong type.
+    Invalid(String, serde_json::Error),
+    /// A layer has a line or flag that is not understood.
+    Syntax(String, String),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoadError::Io(layer, err) => write!(formatter, "cannot read {}: {}", layer, err),
+            LoadError::Invalid(layer, err) => write!(formatter, "invalid {}: {}", layer, err),
+            LoadError::Syntax(layer, message) => {
+                write!(formatter, "invalid {}: {}", layer, message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+struct Layer {
+    name: String,
+    /// The fields as a JSON object. The timeout can only be checked against
+    /// the bounds of the `Defaults` passed to `load`, so the fields are read
+    /// again there.
+    fields: Value,
+}
+
+/// Collects layers, each overriding the ones added before it.
+#[derive(Default)]
+pub struct Loader {
+    layers: Vec<Layer>,
+}
+
+/// The fields that may also be given as a number.
+const NUMERIC_FIELDS: &[&str] = &["timeout", "priority"];
+
+/// Reads the value of `field` from the environment or the command line.
+/// `resource` is always a string, so `null` or `404` is a path like any
+/// other. `timeout` and `priority` are read as a number when they are one,
+/// such as `5`, and as a string otherwise, such as `5m` or `High`.
+fn parse_value(field: &str, value: String) -> Value {
+    if NUMERIC_FIELDS.contains(&field) {
+        if let Ok(number) = serde_json::from_str::<Number>(&value) {
+            return Value::Number(number);
+        }
+    }
+    Value::String(value)
+}
+
+/// Removes one pair of matching `"` or `'` around the value of a `.env`
+/// line, so that `"/a b"` is the path `/a b` and `"5"` is the number `5`.
+fn unquote(value: &str) -> &str {
+    for quote in &['"', '\''] {
+        if let Some(inner) = value
+            .strip_prefix(*quote)
+            .and_then(|rest| rest.strip_suffix(*quote))
+        {
+            return inner;
+        }
+    }
+    value
+}
+
+/// Reads the fields of the layer `name` with its timeout within `bounds`.
+fn read(name: &str, fields: &Value, bounds: TimeoutBounds) -> Result<PartialRequest, LoadError> {
+    PartialRequestSeed { bounds }
+        .deserialize(fields)
+        .map_err(|err| LoadError::Invalid(name.to_owned(), err))
+}
+
+impl Loader {
+    pub fn new() -> Self {
+        Loader::default()
+    }
+
+    /// Adds a layer once its fields have the right types, so that a typo is
+    /// reported by the call that added it.
+    fn push(&mut self, name: String, fields: Value) -> Result<&mut Self, LoadError> {
+        read(&name, &fields, TimeoutBounds::ANY)?;
+        self.layers.push(Layer { name, fields });
+        Ok(self)
+    }
+
+    /// Adds a layer from a JSON object with some of the fields of a
+    /// `Request`.
+    pub fn json(&mut self, name: &str, json: &str) -> Result<&mut Self, LoadError> {
+        let fields =
+            serde_json::from_str(json).map_err(|err| LoadError::Invalid(name.to_owned(), err))?;
+        self.push(name.to_owned(), fields)
+    }
+
+    /// Adds a layer from a JSON file.
+    pub fn file<P: AsRef<Path>>(&mut self, path: P) -> Result<&mut Self, LoadError> {
+        let name = path.as_ref().display().to_string();
+        let json = fs::read_to_string(path).map_err(|err| LoadError::Io(name.clone(), err))?;
+        self.json(&name, &json)
+    }
+
+    /// Adds a layer from `KEY=VALUE` lines like those of a `.env` file.
+    /// Blank lines and lines starting with `#` are skipped, and so are keys
+    /// without the `REQUEST_` prefix, which belong to someone else. A value
+    /// may be put in double or single quotes, which are not part of it.
+    pub fn env_file(&mut self, name: &str, contents: &str) -> Result<&mut Self, LoadError> {
+        let mut vars = Vec::new();
+        for line in contents.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            match line.find('=') {
+                Some(i) => {
+                    let value = unquote(line[i + 1..].trim());
+                    vars.push((line[..i].trim().to_owned(), value.to_owned()))
+                }
+                None => {
+                    return Err(LoadError::Syntax(
+                        name.to_owned(),
+                        format!("expected `KEY=VALUE`, found `{}`", line),
+                    ))
+                }
+            }
+        }
+        self.vars(name, vars)
+    }
+
+    /// Adds a layer from environment variables, such as `std::env::vars()`.
+    /// `REQUEST_TIMEOUT` sets `timeout`, and so on. Other variables are
+    /// skipped.
+    pub fn env<I>(&mut self, vars: I) -> Result<&mut Self, LoadError>
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        self.vars("environment", vars)
+    }
+
+    fn vars<I>(&mut self, name: &str, vars: I) -> Result<&mut Self, LoadError>
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        let mut fields = Map::new();
+        for (key, value) in vars {
+            if !key.starts_with(ENV_PREFIX) {
+                continue;
+            }
+            let field = key[ENV_PREFIX.len()..].to_lowercase();
+            if FIELDS.contains(&field.as_str()) {
+                let value = parse_value(&field, value);
+                fields.insert(field, value);
+            }
+        }
+        self.push(name.to_owned(), Value::Object(fields))
+    }
+
+    /// Adds a layer from command-line flags, `--timeout 5` or `--timeout=5`.
+    /// Unlike variables, an unknown flag is an error, since it can only be
+    /// a typo.
+    pub fn args<I>(&mut self, args: I) -> Result<&mut Self, LoadError>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let syntax = |message: String| LoadError::Syntax("command line".to_owned(), message);
+        let mut fields = Map::new();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let flag = match arg.strip_prefix("--") {
+                Some(flag) => flag,
+                None => return Err(syntax(format!("expected a flag, found `{}`", arg))),
+            };
+            let (field, value) = match flag.find('=') {
+                Some(i) => (&flag[..i], flag[i + 1..].to_owned()),
+                None => match args.next() {
+                    Some(value) => (flag, value),
+                    None => return Err(syntax(format!("`--{}` needs a value", flag))),
+                },
+            };
+            if !FIELDS.contains(&field) {
+                return Err(syntax(format!(
+                    "unknown flag `--{}`, expected one of --{}",
+                    field,
+                    FIELDS.join(", --")
+                )));
+            }
+            fields.insert(field.to_owned(), parse_value(field, value));
+        }
+        self.push("command line".to_owned(), Value::Object(fields))
+    }
+
+    /// Merges the layers field by field, and fills the fields that none of
+    /// them set from `defaults`. Each layer's timeout is read within the
+    /// bounds of `defaults`, and one outside them is an error.
+    pub fn load(self, defaults: &Defaults) -> Result<(Request, Sources), LoadError> {
+        let mut merged = PartialRequest::default();
+        let mut sources = Sources {
+            resource: "default".to_owned(),
+            timeout: "default".to_owned(),
+            priority: "default".to_owned(),
+        };
+        for layer in self.layers {
+            let fields = read(&layer.name, &layer.fields, defaults.timeout_bounds)?;
+            if let Some(resource) = fields.resource {
+                merged.resource = Some(resource);
+                sources.resource = layer.name.clone();
+            }
+            if let Some(timeout) = fields.timeout {
+                merged.timeout = Some(timeout);
+                sources.timeout = layer.name.clone();
+            }
+            if let Some(priority) = fields.priority {
+                merged.priority = Some(priority);
+                sources.priority = layer.name;
+            }
+        }
+        Ok((merged.or(defaults), sources))
+    }
+}
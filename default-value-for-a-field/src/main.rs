@@ -2,7 +2,17 @@
 //!
 //! Source: [https://serde.rs/attr-default.html](https://serde.rs/attr-default.html)
 
-use serde::Deserialize;
+mod layers;
+
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::fmt;
+use std::time::Duration;
+
+use serde::de::{self, DeserializeSeed, Deserializer, MapAccess, SeqAccess, Unexpected, Visitor};
+use serde::{Deserialize, Serialize, Serializer};
+
+use layers::Loader;
 
 /// A Request.
 #[derive(Deserialize, Debug)]
@@ -28,17 +38,204 @@ fn default_resource() -> String {
     "/".to_string()
 }
 
-/// Timeout in seconds.
-#[derive(Deserialize, Debug)]
-struct Timeout(u32);
+/// How long to wait for a `Request`.
+///
+/// Deserialized from a number of seconds, such as `5`, or a string with a
+/// unit, such as `"5s"`, `"250ms"`, `"2m"` or `"1h"`. Serialized as a string
+/// in the largest unit that represents it exactly, so `"120s"` comes back as
+/// `"2m"`. It must lie within `TimeoutBounds::default()`, or within the
+/// bounds of a `TimeoutSeed` or of the `Defaults` a request is read with.
+#[derive(Debug, Clone, PartialEq)]
+struct Timeout(Duration);
 impl Default for Timeout {
     fn default() -> Self {
-        Timeout(30)
+        Timeout::from_secs(30)
+    }
+}
+
+/// The units a timeout can be written in, from the largest, with their
+/// length in milliseconds.
+const TIMEOUT_UNITS: &[(&str, u64)] = &[("h", 3_600_000), ("m", 60_000), ("s", 1_000), ("ms", 1)];
+
+impl Timeout {
+    fn from_secs(secs: u64) -> Self {
+        Timeout(Duration::from_secs(secs))
+    }
+
+    fn duration(&self) -> Duration {
+        self.0
+    }
+
+    /// Parses a number with an optional unit, which defaults to seconds.
+    fn parse(value: &str) -> Result<Duration, String> {
+        let value = value.trim();
+        let split = value
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(value.len());
+        let (number, unit) = (&value[..split], value[split..].trim());
+        let invalid = || {
+            format!(
+                "invalid timeout `{}`, expected a number of ms, s, m or h such as `250ms`",
+                value
+            )
+        };
+        let number: u64 = number.parse().map_err(|_| invalid())?;
+        let unit = match unit {
+            "" => 1_000,
+            unit => match TIMEOUT_UNITS.iter().find(|(name, _)| *name == unit) {
+                Some((_, millis)) => *millis,
+                None => return Err(invalid()),
+            },
+        };
+        number
+            .checked_mul(unit)
+            .map(Duration::from_millis)
+            .ok_or_else(|| format!("timeout `{}` is too long", value))
+    }
+}
+
+/// Writes the canonical form, such as `250ms` or `2m`.
+impl fmt::Display for Timeout {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        let millis = self.0.as_millis();
+        if millis == 0 {
+            return formatter.write_str("0s");
+        }
+        for (name, length) in TIMEOUT_UNITS {
+            let length = u128::from(*length);
+            if millis.is_multiple_of(length) {
+                return write!(formatter, "{}{}", millis / length, name);
+            }
+        }
+        unreachable!("every whole number of milliseconds is a multiple of 1ms")
+    }
+}
+
+impl Serialize for Timeout {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+/// The shortest and longest timeouts that are accepted. By default any
+/// timeout from 1ms up to `u32::MAX` seconds, so that a service with its own
+/// limit sets it in `Defaults` instead of inheriting one from here.
+#[derive(Debug, Clone, Copy)]
+struct TimeoutBounds {
+    min: Duration,
+    max: Duration,
+}
+
+impl Default for TimeoutBounds {
+    fn default() -> Self {
+        TimeoutBounds {
+            min: Duration::from_millis(1),
+            max: Duration::from_secs(u64::from(u32::MAX)),
+        }
+    }
+}
+
+impl TimeoutBounds {
+    /// Every timeout that can be written, to check the form of a timeout
+    /// before the bounds it has to lie within are known.
+    const ANY: TimeoutBounds = TimeoutBounds {
+        min: Duration::ZERO,
+        max: Duration::MAX,
+    };
+
+    fn check(&self, duration: Duration) -> Result<Timeout, String> {
+        let timeout = Timeout(duration);
+        if duration < self.min {
+            Err(format!(
+                "timeout {} is below the minimum of {}",
+                timeout,
+                Timeout(self.min)
+            ))
+        } else if duration > self.max {
+            Err(format!(
+                "timeout {} is above the maximum of {}",
+                timeout,
+                Timeout(self.max)
+            ))
+        } else {
+            Ok(timeout)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Timeout {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        TimeoutSeed {
+            bounds: TimeoutBounds::default(),
+        }
+        .deserialize(deserializer)
+    }
+}
+
+/// Deserializes a `Timeout` within other `bounds` than the default ones.
+#[derive(Clone, Copy)]
+struct TimeoutSeed {
+    bounds: TimeoutBounds,
+}
+
+impl<'de> DeserializeSeed<'de> for TimeoutSeed {
+    type Value = Timeout;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for TimeoutSeed {
+    type Value = Timeout;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a number of seconds, or a string such as \"250ms\", \"5s\" or \"2m\"")
+    }
+
+    fn visit_u64<E>(self, value: u64) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        self.bounds
+            .check(Duration::from_secs(value))
+            .map_err(E::custom)
+    }
+
+    fn visit_i64<E>(self, value: i64) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        if value < 0 {
+            return Err(E::custom(format!("timeout {}s is negative", value)));
+        }
+        self.visit_u64(value as u64)
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        let duration = Timeout::parse(value).map_err(E::custom)?;
+        self.bounds.check(duration).map_err(E::custom)
     }
 }
 
 /// Priority level of the `Request`.
-#[derive(Deserialize, Debug)]
+///
+/// Serialized as the variant name. Deserialized from any spelling of it,
+/// ignoring case, `-` and `_`, so `"ExtraHigh"`, `"extra-high"` and
+/// `"EXTRA_HIGH"` are the same, or from its level, 0 to 4.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
 enum Priority {
     ExtraHigh,
     High,
@@ -47,9 +244,499 @@ enum Priority {
     ExtraLow,
 }
 impl Priority {
+    /// From the lowest level, 0, to the highest, 4.
+    const LEVELS: [Priority; 5] = [
+        Priority::ExtraLow,
+        Priority::Low,
+        Priority::Normal,
+        Priority::High,
+        Priority::ExtraHigh,
+    ];
+
     fn lowest() -> Self {
         Priority::ExtraLow
     }
+
+    fn level(&self) -> u8 {
+        match self {
+            Priority::ExtraLow => 0,
+            Priority::Low => 1,
+            Priority::Normal => 2,
+            Priority::High => 3,
+            Priority::ExtraHigh => 4,
+        }
+    }
+
+    fn from_level(level: u64) -> Option<Self> {
+        usize::try_from(level)
+            .ok()
+            .and_then(|level| Priority::LEVELS.get(level))
+            .cloned()
+    }
+
+    /// The variant named `name`, in any case and with or without `-` or `_`
+    /// between the words.
+    fn from_name(name: &str) -> Option<Self> {
+        let name: String = name
+            .chars()
+            .filter(|c| *c != '-' && *c != '_')
+            .map(|c| c.to_ascii_lowercase())
+            .collect();
+        match name.as_str() {
+            "extralow" => Some(Priority::ExtraLow),
+            "low" => Some(Priority::Low),
+            "normal" => Some(Priority::Normal),
+            "high" => Some(Priority::High),
+            "extrahigh" => Some(Priority::ExtraHigh),
+            _ => None,
+        }
+    }
+}
+
+/// Ordered by level, so `ExtraHigh` is the greatest.
+impl Ord for Priority {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.level().cmp(&other.level())
+    }
+}
+
+impl PartialOrd for Priority {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<'de> Deserialize<'de> for Priority {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(PriorityVisitor)
+    }
+}
+
+struct PriorityVisitor;
+
+fn out_of_range<E: de::Error>(level: impl fmt::Display) -> E {
+    E::custom(format!(
+        "priority level {} is out of range, expected 0 (ExtraLow) to 4 (ExtraHigh)",
+        level
+    ))
+}
+
+impl<'de> Visitor<'de> for PriorityVisitor {
+    type Value = Priority;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str(
+            "a priority level from 0 to 4, or one of ExtraLow, Low, Normal, High, ExtraHigh",
+        )
+    }
+
+    fn visit_u64<E>(self, value: u64) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Priority::from_level(value).ok_or_else(|| out_of_range(value))
+    }
+
+    fn visit_i64<E>(self, value: i64) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        if value < 0 {
+            return Err(out_of_range(value));
+        }
+        self.visit_u64(value as u64)
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Priority::from_name(value).ok_or_else(|| E::invalid_value(Unexpected::Str(value), &self))
+    }
+}
+
+/// A field value, and whether it was in the input or filled in by a default.
+/// Once a default has run, the value alone no longer tells the two apart.
+#[derive(Debug, Clone, PartialEq)]
+enum Field<T> {
+    Explicit(T),
+    Defaulted(T),
+}
+
+impl<T> Field<T> {
+    fn value(&self) -> &T {
+        match self {
+            Field::Explicit(value) | Field::Defaulted(value) => value,
+        }
+    }
+
+    fn into_value(self) -> T {
+        match self {
+            Field::Explicit(value) | Field::Defaulted(value) => value,
+        }
+    }
+
+    fn is_defaulted(&self) -> bool {
+        match self {
+            Field::Explicit(_) => false,
+            Field::Defaulted(_) => true,
+        }
+    }
+}
+
+/// Used by `#[serde(default)]`, so only when the field is missing.
+impl<T: Default> Default for Field<T> {
+    fn default() -> Self {
+        Field::Defaulted(T::default())
+    }
+}
+
+/// A field that is deserialized at all was in the input.
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Field<T> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        T::deserialize(deserializer).map(Field::Explicit)
+    }
+}
+
+/// Serializes only the value. Skipping defaulted fields is up to the
+/// containing struct, with `skip_serializing_if = "Field::is_defaulted"`.
+impl<T: Serialize> Serialize for Field<T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.value().serialize(serializer)
+    }
+}
+
+/// A `Request` that remembers which fields the client sent, for the audit
+/// log. Serializing it writes only those fields, so the log shows what was
+/// asked for rather than what the defaults made of it.
+#[derive(Serialize, Deserialize, Debug)]
+struct AuditedRequest {
+    #[serde(
+        default = "defaulted_resource",
+        skip_serializing_if = "Field::is_defaulted"
+    )]
+    resource: Field<String>,
+
+    #[serde(default, skip_serializing_if = "Field::is_defaulted")]
+    timeout: Field<Timeout>,
+
+    #[serde(
+        default = "defaulted_priority",
+        skip_serializing_if = "Field::is_defaulted"
+    )]
+    priority: Field<Priority>,
+}
+
+fn defaulted_resource() -> Field<String> {
+    Field::Defaulted(default_resource())
+}
+
+fn defaulted_priority() -> Field<Priority> {
+    Field::Defaulted(Priority::lowest())
+}
+
+impl AuditedRequest {
+    /// The names of the fields that were filled in by a default.
+    fn defaulted(&self) -> Vec<&'static str> {
+        let fields = [
+            ("resource", self.resource.is_defaulted()),
+            ("timeout", self.timeout.is_defaulted()),
+            ("priority", self.priority.is_defaulted()),
+        ];
+        fields
+            .iter()
+            .filter(|(_, defaulted)| *defaulted)
+            .map(|(name, _)| *name)
+            .collect()
+    }
+}
+
+impl From<AuditedRequest> for Request {
+    fn from(audited: AuditedRequest) -> Self {
+        Request {
+            resource: audited.resource.into_value(),
+            timeout: audited.timeout.into_value(),
+            priority: audited.priority.into_value(),
+        }
+    }
+}
+
+/// Defaults that are only known at runtime, such as those of the tenant a
+/// request was sent for. `Defaults::default()` are the static defaults that
+/// `#[derive(Deserialize)]` uses, and `Defaults::new` builds any others.
+#[derive(Debug, Clone)]
+struct Defaults {
+    resource: String,
+    timeout: Timeout,
+    priority: Priority,
+    /// The timeouts a request may set.
+    timeout_bounds: TimeoutBounds,
+}
+
+impl Default for Defaults {
+    fn default() -> Self {
+        Defaults {
+            resource: default_resource(),
+            timeout: Timeout::default(),
+            priority: Priority::lowest(),
+            timeout_bounds: TimeoutBounds::default(),
+        }
+    }
+}
+
+impl Defaults {
+    /// Fails if `timeout` is outside `timeout_bounds`, since a request that
+    /// leaves its timeout out would then get one it could not have sent.
+    fn new(
+        resource: String,
+        timeout: Timeout,
+        priority: Priority,
+        timeout_bounds: TimeoutBounds,
+    ) -> Result<Self, String> {
+        timeout_bounds
+            .check(timeout.duration())
+            .map_err(|err| format!("invalid default: {}", err))?;
+        Ok(Defaults {
+            resource,
+            timeout,
+            priority,
+            timeout_bounds,
+        })
+    }
+}
+
+/// The fields of a `Request` that were present in the input. A field that is
+/// `null` counts as missing.
+#[derive(Debug, Default)]
+struct PartialRequest {
+    resource: Option<String>,
+    timeout: Option<Timeout>,
+    priority: Option<Priority>,
+}
+
+/// Reads the timeout within `TimeoutBounds::default()`, like `Timeout`.
+impl<'de> Deserialize<'de> for PartialRequest {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        PartialRequestSeed {
+            bounds: TimeoutBounds::default(),
+        }
+        .deserialize(deserializer)
+    }
+}
+
+/// Deserializes a `PartialRequest` with its timeout within `bounds`. Like
+/// the derived impl it replaces, it ignores unknown fields and rejects
+/// duplicate ones.
+#[derive(Clone, Copy)]
+struct PartialRequestSeed {
+    bounds: TimeoutBounds,
+}
+
+impl<'de> DeserializeSeed<'de> for PartialRequestSeed {
+    type Value = PartialRequest;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de> Visitor<'de> for PartialRequestSeed {
+    type Value = PartialRequest;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a request")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut resource = None;
+        let mut timeout = None;
+        let mut priority = None;
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "resource" => {
+                    if resource.is_some() {
+                        return Err(de::Error::duplicate_field("resource"));
+                    }
+                    resource = Some(map.next_value()?);
+                }
+                "timeout" => {
+                    if timeout.is_some() {
+                        return Err(de::Error::duplicate_field("timeout"));
+                    }
+                    let seed = OptionalTimeoutSeed(TimeoutSeed {
+                        bounds: self.bounds,
+                    });
+                    timeout = Some(map.next_value_seed(seed)?);
+                }
+                "priority" => {
+                    if priority.is_some() {
+                        return Err(de::Error::duplicate_field("priority"));
+                    }
+                    priority = Some(map.next_value()?);
+                }
+                _ => {
+                    map.next_value::<de::IgnoredAny>()?;
+                }
+            }
+        }
+        Ok(PartialRequest {
+            resource: resource.flatten(),
+            timeout: timeout.flatten(),
+            priority: priority.flatten(),
+        })
+    }
+}
+
+/// Deserializes an optional `Timeout` within the bounds of a `TimeoutSeed`,
+/// where `null` is `None`.
+struct OptionalTimeoutSeed(TimeoutSeed);
+
+impl<'de> DeserializeSeed<'de> for OptionalTimeoutSeed {
+    type Value = Option<Timeout>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_option(self)
+    }
+}
+
+impl<'de> Visitor<'de> for OptionalTimeoutSeed {
+    type Value = Option<Timeout>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("null or ")?;
+        self.0.expecting(formatter)
+    }
+
+    fn visit_none<E>(self) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(None)
+    }
+
+    fn visit_unit<E>(self) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(None)
+    }
+
+    fn visit_some<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        self.0.deserialize(deserializer).map(Some)
+    }
+}
+
+impl PartialRequest {
+    /// Fills the missing fields from `defaults`.
+    fn or(self, defaults: &Defaults) -> Request {
+        Request {
+            resource: self.resource.unwrap_or_else(|| defaults.resource.clone()),
+            timeout: self.timeout.unwrap_or_else(|| defaults.timeout.clone()),
+            priority: self.priority.unwrap_or_else(|| defaults.priority.clone()),
+        }
+    }
+
+    /// Like `or`, but remembers which fields came from `defaults`.
+    fn or_audited(self, defaults: &Defaults) -> AuditedRequest {
+        fn field<T: Clone>(value: Option<T>, default: &T) -> Field<T> {
+            value.map_or_else(|| Field::Defaulted(default.clone()), Field::Explicit)
+        }
+
+        AuditedRequest {
+            resource: field(self.resource, &defaults.resource),
+            timeout: field(self.timeout, &defaults.timeout),
+            priority: field(self.priority, &defaults.priority),
+        }
+    }
+}
+
+/// Deserializes a `Request`, taking missing fields from `defaults` instead of
+/// the static defaults, and checking its timeout against their bounds.
+///
+/// `#[serde(default = "...")]` can only name a function without arguments,
+/// so it has no way to see a runtime value. A `DeserializeSeed` is
+/// deserialized like a `Deserialize` type, but is a value itself and can
+/// carry that context along.
+#[derive(Clone, Copy)]
+struct RequestSeed<'a> {
+    defaults: &'a Defaults,
+}
+
+impl<'de, 'a> DeserializeSeed<'de> for RequestSeed<'a> {
+    type Value = Request;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        PartialRequestSeed {
+            bounds: self.defaults.timeout_bounds,
+        }
+        .deserialize(deserializer)
+        .map(|partial| partial.or(self.defaults))
+    }
+}
+
+/// Deserializes a sequence of `Request`s, all with the same `defaults`.
+struct RequestsSeed<'a> {
+    defaults: &'a Defaults,
+}
+
+impl<'de, 'a> DeserializeSeed<'de> for RequestsSeed<'a> {
+    type Value = Vec<Request>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de, 'a> Visitor<'de> for RequestsSeed<'a> {
+    type Value = Vec<Request>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a sequence of requests")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut requests = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        let seed = RequestSeed {
+            defaults: self.defaults,
+        };
+        while let Some(request) = seq.next_element_seed(seed)? {
+            requests.push(request);
+        }
+        Ok(requests)
+    }
 }
 
 fn main() {
@@ -71,4 +758,290 @@ fn main() {
 
     println!("First  request:\n{:?}", requests[0]);
     println!("Second request:\n{:?}", requests[1]);
+
+    // The same requests, sent for different tenants, get different defaults.
+    let tenants = [
+        (
+            "acme",
+            Defaults::new(
+                "/acme".to_string(),
+                Timeout::from_secs(10),
+                Priority::lowest(),
+                TimeoutBounds::default(),
+            )
+            .unwrap(),
+        ),
+        (
+            "globex",
+            Defaults::new(
+                default_resource(),
+                Timeout::from_secs(120),
+                Priority::Normal,
+                TimeoutBounds::default(),
+            )
+            .unwrap(),
+        ),
+    ];
+    for (tenant, defaults) in &tenants {
+        let seed = RequestsSeed { defaults };
+        let mut de = serde_json::Deserializer::from_str(json);
+        let requests = seed.deserialize(&mut de).unwrap();
+        println!("Requests for {}:\n{:?}", tenant, requests);
+    }
+
+    let seed = RequestSeed {
+        defaults: &Defaults::default(),
+    };
+    let mut de = serde_json::Deserializer::from_str(r#"{"resource": "/users"}"#);
+    println!("Static defaults:\n{:?}", seed.deserialize(&mut de).unwrap());
+
+    // The same fields, set by several layers. Each field comes from the last
+    // layer that set it, and only the fields no layer set use the defaults.
+    let mut loader = Loader::new();
+    loader
+        .json("defaults.json", r#"{"resource": "/users", "timeout": 60}"#)
+        .unwrap()
+        .env_file(
+            ".env",
+            "# Shared with other tools\nREQUEST_TIMEOUT=15\nEDITOR=vi\n",
+        )
+        .unwrap()
+        .env(vec![("REQUEST_PRIORITY".to_string(), "Normal".to_string())])
+        .unwrap()
+        .args(vec!["--timeout=5".to_string()])
+        .unwrap();
+    let (request, sources) = loader.load(&Defaults::default()).unwrap();
+    println!("Layered:\n{:?}\n{}", request, sources);
+    assert_eq!(sources.resource, "defaults.json");
+    assert_eq!(sources.timeout, "command line");
+    assert_eq!(sources.priority, "environment");
+
+    let (request, sources) = Loader::new().load(&Defaults::default()).unwrap();
+    println!("No layers:\n{:?}\n{}", request, sources);
+
+    // A resource is a string whatever it looks like.
+    let mut loader = Loader::new();
+    loader
+        .env(vec![("REQUEST_RESOURCE".to_string(), "null".to_string())])
+        .unwrap();
+    let (request, sources) = loader.load(&Defaults::default()).unwrap();
+    assert_eq!(request.resource, "null");
+    assert_eq!(sources.resource, "environment");
+    let mut loader = Loader::new();
+    loader
+        .args(vec!["--resource".to_string(), "404".to_string()])
+        .unwrap();
+    assert_eq!(loader.load(&Defaults::default()).unwrap().0.resource, "404");
+
+    // Quotes around a `.env` value are not part of it, so a quoted number
+    // is still a number.
+    let mut loader = Loader::new();
+    loader
+        .env_file(".env", "REQUEST_RESOURCE=\"/a b\"\nREQUEST_TIMEOUT='5'\n")
+        .unwrap();
+    let (request, _) = loader.load(&Defaults::default()).unwrap();
+    assert_eq!(request.resource, "/a b");
+    assert_eq!(request.timeout, Timeout::from_secs(5));
+    // Quotes that do not match are kept, and `"High'` is no priority.
+    assert!(Loader::new()
+        .env_file(".env", "REQUEST_PRIORITY=\"High'")
+        .is_err());
+
+    let errors = vec![
+        Loader::new().file("missing.json").map(|_| ()),
+        Loader::new()
+            .env(vec![("REQUEST_TIMEOUT".to_string(), "soon".to_string())])
+            .map(|_| ()),
+        Loader::new()
+            .args(vec!["--timeuot".to_string(), "5".to_string()])
+            .map(|_| ()),
+        Loader::new()
+            .env_file(".env", "REQUEST_TIMEOUT")
+            .map(|_| ()),
+    ];
+    for error in errors {
+        println!("Error: {}", error.unwrap_err());
+    }
+
+    // Whether a field was sent or defaulted is lost in a `Request`, but kept
+    // in an `AuditedRequest`.
+    let audited: Vec<AuditedRequest> = serde_json::from_str(json).unwrap();
+    for request in &audited {
+        println!(
+            "Audited:\n{:?}\ndefaulted: {:?}, sent: {}",
+            request,
+            request.defaulted(),
+            serde_json::to_string(request).unwrap()
+        );
+    }
+    assert_eq!(audited[0].defaulted(), ["timeout", "priority"]);
+    assert_eq!(audited[1].timeout, Field::Explicit(Timeout::from_secs(5)));
+    assert_eq!(
+        serde_json::to_string(&audited[0]).unwrap(),
+        r#"{"resource":"/users"}"#
+    );
+
+    // A client that sends the default value still counts as explicit.
+    let request: AuditedRequest = serde_json::from_str(r#"{"timeout": 30}"#).unwrap();
+    assert_eq!(request.timeout, Field::Explicit(Timeout::from_secs(30)));
+    assert_eq!(request.defaulted(), ["resource", "priority"]);
+    println!("Effective request:\n{:?}", Request::from(request));
+
+    let partial: PartialRequest = serde_json::from_str(r#"{"priority": "Low"}"#).unwrap();
+    let request = partial.or_audited(&tenants[1].1);
+    println!(
+        "Audited for globex:\n{:?}\ndefaulted: {:?}",
+        request,
+        request.defaulted()
+    );
+
+    // Clients spell priorities in many ways, and some send levels instead.
+    let spellings = r#"["High", "high", "HIGH", "extra-high", "extra_high", "ExtraHigh", 0, 4]"#;
+    let priorities: Vec<Priority> = serde_json::from_str(spellings).unwrap();
+    println!("Priorities:\n{:?}", priorities);
+    assert_eq!(priorities[1], Priority::High);
+    assert_eq!(priorities[4], Priority::ExtraHigh);
+    assert_eq!(priorities[6], Priority::lowest());
+    assert!(Priority::ExtraHigh > Priority::High);
+    assert_eq!(priorities.iter().max(), Some(&Priority::ExtraHigh));
+    assert_eq!(
+        serde_json::to_string(&priorities[3]).unwrap(),
+        r#""ExtraHigh""#
+    );
+
+    // Levels past `u32::MAX` are out of range too, not wrapped around on a
+    // 32-bit target.
+    for invalid in &["5", "-1", "4294967296", r#""urgent""#, "2.5"] {
+        let err = serde_json::from_str::<Priority>(invalid).unwrap_err();
+        println!("Error: {}", err);
+    }
+
+    // Timeouts carry their unit, and are written back in the largest unit
+    // that represents them exactly.
+    let timeouts = r#"[5, "5", "5s", "250ms", "2m", "120s", "1h", "1500ms"]"#;
+    let timeouts: Vec<Timeout> = serde_json::from_str(timeouts).unwrap();
+    println!("Timeouts:\n{:?}", timeouts);
+    assert_eq!(timeouts[0], timeouts[2]);
+    assert_eq!(timeouts[3].duration(), Duration::from_millis(250));
+    assert_eq!(timeouts[4], timeouts[5]);
+    assert_eq!(
+        serde_json::to_string(&timeouts).unwrap(),
+        r#"["5s","5s","5s","250ms","2m","2m","1h","1500ms"]"#
+    );
+
+    // Without other bounds, any timeout from 1ms up is accepted.
+    let long: Timeout = serde_json::from_str(r#""2h""#).unwrap();
+    assert_eq!(long, Timeout::from_secs(7200));
+    assert_eq!(
+        serde_json::from_str::<Timeout>("86400").unwrap(),
+        Timeout::from_secs(86400)
+    );
+
+    for invalid in &[
+        r#""5 minutes""#,
+        r#""0ms""#,
+        r#""5000000000s""#,
+        "-3",
+        "1.5",
+    ] {
+        let err = serde_json::from_str::<Timeout>(invalid).unwrap_err();
+        println!("Error: {}", err);
+    }
+
+    // Tighter bounds for a single deserialization.
+    let seed = TimeoutSeed {
+        bounds: TimeoutBounds {
+            min: Duration::from_millis(100),
+            max: Duration::from_secs(10),
+        },
+    };
+    for timeout in &[r#""500ms""#, r#""50ms""#, "30"] {
+        let mut de = serde_json::Deserializer::from_str(timeout);
+        match seed.deserialize(&mut de) {
+            Ok(timeout) => println!("Bounded: {}", timeout),
+            Err(err) => println!("Bounded error: {}", err),
+        }
+    }
+
+    // Bounds in `Defaults` apply to the requests read with them, whether
+    // through a `RequestSeed` or a `Loader`.
+    let defaults = Defaults::new(
+        default_resource(),
+        Timeout::default(),
+        Priority::lowest(),
+        TimeoutBounds {
+            min: Duration::from_secs(1),
+            max: Duration::from_secs(60),
+        },
+    )
+    .unwrap();
+    let seed = RequestSeed {
+        defaults: &defaults,
+    };
+    let mut de = serde_json::Deserializer::from_str(r#"{"timeout": "30s"}"#);
+    assert_eq!(
+        seed.deserialize(&mut de).unwrap().timeout,
+        Timeout::from_secs(30)
+    );
+    let mut de = serde_json::Deserializer::from_str(r#"{"timeout": "2h"}"#);
+    let err = seed.deserialize(&mut de).unwrap_err();
+    assert!(err.to_string().contains("above the maximum of 1m"));
+    println!("Bounded request error: {}", err);
+
+    let mut loader = Loader::new();
+    loader
+        .args(vec!["--timeout".to_string(), "2h".to_string()])
+        .unwrap();
+    let err = loader.load(&defaults).unwrap_err();
+    assert!(err
+        .to_string()
+        .starts_with("invalid command line: timeout 2h"));
+    println!("Error: {}", err);
+
+    // Bounds in `Defaults` can also be wider than the default ones, down to
+    // no timeout at all and past `u32::MAX` seconds.
+    let defaults = Defaults::new(
+        default_resource(),
+        Timeout::default(),
+        Priority::lowest(),
+        TimeoutBounds {
+            min: Duration::ZERO,
+            max: Duration::from_secs(1 << 40),
+        },
+    )
+    .unwrap();
+    let seed = RequestSeed {
+        defaults: &defaults,
+    };
+    for (timeout, secs) in &[("0", 0), ("5000000000", 5_000_000_000)] {
+        let json = format!(r#"{{"timeout": {}}}"#, timeout);
+        let mut de = serde_json::Deserializer::from_str(&json);
+        assert_eq!(
+            seed.deserialize(&mut de).unwrap().timeout,
+            Timeout::from_secs(*secs)
+        );
+    }
+    let mut loader = Loader::new();
+    loader
+        .args(vec!["--timeout".to_string(), "0s".to_string()])
+        .unwrap();
+    let (request, _) = loader.load(&defaults).unwrap();
+    assert_eq!(request.timeout, Timeout::from_secs(0));
+
+    // The default timeout has to lie within the bounds too.
+    let err = Defaults::new(
+        default_resource(),
+        Timeout::from_secs(120),
+        Priority::lowest(),
+        TimeoutBounds {
+            min: Duration::from_secs(1),
+            max: Duration::from_secs(60),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        "invalid default: timeout 2m is above the maximum of 1m"
+    );
+    println!("Error: {}", err);
 }
@@ -1,3 +1,6 @@
+mod remote;
+mod versioned;
+
 mod other_crate {
     #[derive(Debug)]
     pub struct Duration {
@@ -25,11 +28,26 @@ mod other_crate {
 use other_crate::Duration;
 use serde::{Deserialize, Serialize};
 
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::net::SocketAddr;
+use std::num::{NonZeroI64, NonZeroU32};
+use std::ops::Range;
+use std::time::SystemTime;
+
+use versioned::{Schema, Versioned};
+
 /// Serde calls this the definition of the remote type. It is just a copy of
 /// the remote data structure. The `remove` attribute gives the path the actual
 /// type we intend to derive code for.
+///
+/// Not every pair of fields is a valid `Duration`, so instead of building one
+/// from the definition with `From`, the `try_from` attribute deserializes the
+/// unchecked fields as a `RawDuration` and converts them with `TryFrom`. A
+/// failed conversion becomes a `de::Error`.
 #[derive(Serialize, Deserialize)]
-#[serde(remote = "Duration")]
+#[serde(remote = "Duration", try_from = "RawDuration")]
 struct DurationDef {
     #[serde(getter = "Duration::seconds")]
     secs: i64,
@@ -37,9 +55,56 @@ struct DurationDef {
     nanos: i32,
 }
 
-impl From<DurationDef> for Duration {
-    fn from(def: DurationDef) -> Duration {
-        Duration::new(def.secs, def.nanos)
+remote::remote_def!(DurationDef => Duration);
+
+/// The fields of a `Duration` as they appear in the input, before they are
+/// checked.
+#[derive(Deserialize)]
+struct RawDuration {
+    secs: i64,
+    nanos: i32,
+}
+
+/// The reasons a `RawDuration` is not a valid `Duration`.
+#[derive(Debug)]
+enum DurationError {
+    /// `nanos` is a whole second or more, in either direction.
+    NanosOutOfRange(i32),
+    /// `secs` and `nanos` are both non-zero but have different signs.
+    MixedSigns { secs: i64, nanos: i32 },
+}
+
+impl fmt::Display for DurationError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DurationError::NanosOutOfRange(nanos) => write!(
+                formatter,
+                "nanos must be between -999999999 and 999999999, got {}",
+                nanos
+            ),
+            DurationError::MixedSigns { secs, nanos } => write!(
+                formatter,
+                "secs and nanos must have the same sign, got secs = {} and nanos = {}",
+                secs, nanos
+            ),
+        }
+    }
+}
+
+impl TryFrom<RawDuration> for Duration {
+    type Error = DurationError;
+
+    fn try_from(raw: RawDuration) -> Result<Duration, DurationError> {
+        if raw.nanos <= -1_000_000_000 || raw.nanos >= 1_000_000_000 {
+            return Err(DurationError::NanosOutOfRange(raw.nanos));
+        }
+        if (raw.secs < 0 && raw.nanos > 0) || (raw.secs > 0 && raw.nanos < 0) {
+            return Err(DurationError::MixedSigns {
+                secs: raw.secs,
+                nanos: raw.nanos,
+            });
+        }
+        Ok(Duration::new(raw.secs, raw.nanos))
     }
 }
 
@@ -47,7 +112,7 @@ impl From<DurationDef> for Duration {
 /// Deserialize impls all along. The `with` attribute gives the path to the
 /// definition for the remote type. Note that the real type of the field is the
 /// remote type, not the definition type.
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Debug)]
 struct Process {
     command_line: String,
 
@@ -55,9 +120,184 @@ struct Process {
     wall_time: Duration,
 }
 
+/// `Process` is at version 4 of its wire format. Older payloads are read
+/// into the shape of a later version until they reach the current one,
+/// without changing any data on the way.
+impl Schema for Process {
+    const VERSION: u64 = 4;
+
+    fn migrate(version: u64, payload: serde_json::Value) -> Result<Self, serde_json::Error> {
+        use process_history::{V1, V2, V3};
+        use serde::de::Error;
+
+        match version {
+            Self::VERSION => serde_json::from_value(payload),
+            3 => Process::try_from(serde_json::from_value::<V3>(payload)?).map_err(Error::custom),
+            _ => {
+                let v2 = match version {
+                    1 => V2::from(serde_json::from_value::<V1>(payload)?),
+                    _ => serde_json::from_value(payload)?,
+                };
+                Process::try_from(v2).map_err(Error::custom)
+            }
+        }
+    }
+}
+
+/// Earlier wire formats of `Process`, each with the migration to a later
+/// version.
+mod process_history {
+    use std::convert::TryFrom;
+
+    use serde::Deserialize;
+
+    use super::{Duration, DurationError, Process, RawDuration};
+
+    /// Version 1: `wall_time` is a float of seconds.
+    #[derive(Deserialize)]
+    pub struct V1 {
+        command_line: String,
+        wall_time: f64,
+    }
+
+    /// Version 2: `wall_time` is a string of seconds with up to nine
+    /// decimals, such as `"42.000001321"`, so that it is exact.
+    #[derive(Deserialize)]
+    pub struct V2 {
+        command_line: String,
+        wall_time: String,
+    }
+
+    impl From<V1> for V2 {
+        fn from(v1: V1) -> V2 {
+            V2 {
+                command_line: v1.command_line,
+                wall_time: format!("{:.9}", v1.wall_time),
+            }
+        }
+    }
+
+    /// Version 3: `wall_time` is split into `secs` and `nanos`, and the
+    /// command is a list of arguments.
+    #[derive(Deserialize)]
+    pub struct V3 {
+        command: Vec<String>,
+        wall_time: RawDuration,
+    }
+
+    /// Version 4, the current `Process`, went back to a single
+    /// `command_line`. A version 2 record still has the command line it was
+    /// stored with, so it skips version 3 and keeps it exactly, rather than
+    /// being split into arguments and joined again.
+    impl TryFrom<V2> for Process {
+        type Error = String;
+
+        fn try_from(v2: V2) -> Result<Process, String> {
+            let wall_time = parse_seconds(&v2.wall_time)
+                .ok_or_else(|| format!("invalid wall_time {:?}", v2.wall_time))?;
+            Ok(Process {
+                command_line: v2.command_line,
+                wall_time: Duration::try_from(wall_time).map_err(|err| err.to_string())?,
+            })
+        }
+    }
+
+    /// A version 3 record only has the arguments, which are joined with
+    /// spaces. Arguments that are empty or contain whitespace or quotes are
+    /// quoted, so that the command line splits back into the same arguments.
+    impl TryFrom<V3> for Process {
+        type Error = DurationError;
+
+        fn try_from(v3: V3) -> Result<Process, DurationError> {
+            let arguments: Vec<String> = v3.command.iter().map(|arg| quote(arg)).collect();
+            Ok(Process {
+                command_line: arguments.join(" "),
+                wall_time: Duration::try_from(v3.wall_time)?,
+            })
+        }
+    }
+
+    /// Quotes `arg` for a POSIX shell if it needs to be.
+    fn quote(arg: &str) -> String {
+        let special = |c: char| c.is_whitespace() || c == '\'' || c == '"' || c == '\\';
+        if !arg.is_empty() && !arg.chars().any(special) {
+            arg.to_owned()
+        } else {
+            format!("'{}'", arg.replace('\'', r"'\''"))
+        }
+    }
+
+    /// Parses a decimal number of seconds such as `"-1.5"` into whole
+    /// seconds and nanoseconds with the same sign.
+    fn parse_seconds(s: &str) -> Option<RawDuration> {
+        let negative = s.starts_with('-');
+        let (whole, fraction) = match s.find('.') {
+            Some(dot) => (&s[..dot], &s[dot + 1..]),
+            None => (s, ""),
+        };
+        if fraction.len() > 9 || !fraction.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        let secs: i64 = whole.parse().ok()?;
+        let nanos: i32 = format!("{:0<9}", fraction).parse().ok()?;
+        Some(RawDuration {
+            secs,
+            nanos: if negative { -nanos } else { nanos },
+        })
+    }
+}
+
 #[derive(Deserialize)]
 struct Helper(#[serde(with = "DurationDef")] Duration);
 
+/// A record made only of foreign types, each going through a shim from
+/// `remote`.
+#[derive(Serialize, Deserialize, Debug)]
+struct Endpoint {
+    #[serde(with = "remote::duration")]
+    timeout: std::time::Duration,
+
+    #[serde(with = "remote::system_time")]
+    created: SystemTime,
+
+    #[serde(with = "remote::socket_addr")]
+    bind: SocketAddr,
+
+    #[serde(with = "remote::socket_addr_str")]
+    upstream: SocketAddr,
+
+    #[serde(with = "remote::socket_addr_tuple")]
+    fallback: SocketAddr,
+
+    #[serde(with = "remote::non_zero")]
+    workers: NonZeroU32,
+
+    #[serde(with = "remote::non_zero")]
+    offset: NonZeroI64,
+
+    #[serde(with = "remote::range")]
+    ports: Range<u16>,
+}
+
+/// Containers of foreign types. Each adapter wraps a remote definition, so
+/// none of these fields need a hand-written module.
+#[derive(Serialize, Deserialize, Debug)]
+struct Schedule {
+    /// Without `default`, a missing `deadline` would be an error.
+    #[serde(default, with = "remote::OptionOf::<DurationDef>")]
+    deadline: Option<Duration>,
+
+    #[serde(with = "remote::VecOf::<DurationDef>")]
+    laps: Vec<Duration>,
+
+    #[serde(with = "remote::HashMapOf::<remote::duration::DurationDef>")]
+    budgets: HashMap<String, std::time::Duration>,
+
+    /// Adapters nest, since they are remote definitions themselves.
+    #[serde(with = "remote::VecOf::<remote::OptionOf<remote::range::RangeDef<u16>>>")]
+    windows: Vec<Option<Range<u16>>>,
+}
+
 fn main() {
     // let duration = DurationDef { secs: 42, nanos: 1234};
 
@@ -76,4 +316,183 @@ fn main() {
 
     let dur = serde_json::from_str(j).map(|a: Helper| a.0).unwrap();
     println!("{:?}", dur);
+
+    let j = r#"
+        {
+            "timeout": {"secs": 30, "nanos": 500000000},
+            "created": {"secs": -1, "nanos": 999999999},
+            "bind": {"ip": "0.0.0.0", "port": 8080},
+            "upstream": "[::1]:9000",
+            "fallback": ["10.0.0.1", 9001],
+            "workers": 4,
+            "offset": -7,
+            "ports": {"start": 8000, "end": 9000}
+        }
+    "#;
+    let endpoint: Endpoint = serde_json::from_str(j).unwrap();
+    println!("{:?}", endpoint);
+
+    // Writing the record back gives a document that reads as the same
+    // record.
+    let serialized = serde_json::to_string(&endpoint).unwrap();
+    let reread: Endpoint = serde_json::from_str(&serialized).unwrap();
+    assert_eq!(serde_json::to_string(&reread).unwrap(), serialized);
+    println!("{}", serialized);
+
+    // Each shim rejects what its foreign type cannot hold, with an error
+    // instead of a panic.
+    let invalid = [
+        (
+            r#""workers": 4"#,
+            r#""workers": 0"#,
+            "invalid value: integer `0`",
+        ),
+        (
+            r#""offset": -7"#,
+            r#""offset": 0"#,
+            "invalid value: integer `0`",
+        ),
+        (
+            r#""upstream": "[::1]:9000""#,
+            r#""upstream": "::1:9000""#,
+            "invalid value: string \"::1:9000\", expected a socket address",
+        ),
+        (
+            r#""fallback": ["10.0.0.1", 9001]"#,
+            r#""fallback": ["10.0.0.1", 90010]"#,
+            "invalid value: integer `90010`",
+        ),
+        (
+            r#""timeout": {"secs": 30, "nanos": 500000000}"#,
+            r#""timeout": {"secs": 5, "nanos": 4000000000}"#,
+            "nanos must be less than 1000000000, got 4000000000",
+        ),
+        (
+            r#""timeout": {"secs": 30, "nanos": 500000000}"#,
+            r#""timeout": {"secs": 18446744073709551615, "nanos": 1000000000}"#,
+            "nanos must be less than 1000000000, got 1000000000",
+        ),
+        (
+            r#""created": {"secs": -1, "nanos": 999999999}"#,
+            r#""created": {"secs": -1, "nanos": 1000000000}"#,
+            "nanos must be less than 1000000000, got 1000000000",
+        ),
+        (
+            r#""created": {"secs": -1, "nanos": 999999999}"#,
+            r#""created": {"secs": 9223372036854775807, "nanos": 4294967295}"#,
+            "nanos must be less than 1000000000",
+        ),
+    ];
+    for (valid, replacement, expected) in &invalid {
+        assert!(j.contains(valid), "{}", valid);
+        let err = serde_json::from_str::<Endpoint>(&j.replace(valid, replacement)).unwrap_err();
+        println!("{} => {}", replacement, err);
+        assert!(err.to_string().starts_with(expected), "{}", err);
+    }
+
+    // How far from the epoch a `SystemTime` reaches depends on the platform,
+    // so the extremes either read or fail with an error, but never panic.
+    for secs in &[i64::MIN, i64::MAX] {
+        let extreme = j.replace(
+            r#""created": {"secs": -1, "nanos": 999999999}"#,
+            &format!(r#""created": {{"secs": {}, "nanos": 999999999}}"#, secs),
+        );
+        match serde_json::from_str::<Endpoint>(&extreme) {
+            Ok(endpoint) => println!("{} => {:?}", secs, endpoint.created),
+            Err(err) => {
+                println!("{} => {}", secs, err);
+                assert!(err.to_string().contains("is not a representable time"));
+            }
+        }
+    }
+
+    // The earliest offset that `secs` can hold writes back the same fields,
+    // or fails with an error, when the platform can represent it.
+    let earliest = j.replace(
+        r#""created": {"secs": -1, "nanos": 999999999}"#,
+        r#""created": {"secs": -9223372036854775808, "nanos": 0}"#,
+    );
+    if let Ok(endpoint) = serde_json::from_str::<Endpoint>(&earliest) {
+        match serde_json::to_value(&endpoint) {
+            Ok(written) => {
+                let read: serde_json::Value = serde_json::from_str(&earliest).unwrap();
+                assert_eq!(written, read);
+            }
+            Err(err) => println!("{:?} => {}", endpoint.created, err),
+        }
+    }
+
+    // The largest values that fit are accepted.
+    let largest = j.replace(
+        r#""timeout": {"secs": 30, "nanos": 500000000}"#,
+        r#""timeout": {"secs": 18446744073709551615, "nanos": 999999999}"#,
+    );
+    let endpoint: Endpoint = serde_json::from_str(&largest).unwrap();
+    assert_eq!(endpoint.timeout, std::time::Duration::MAX);
+
+    let j = r#"
+        {
+            "deadline": {"secs": 60, "nanos": 0},
+            "laps": [{"secs": 1, "nanos": 5}, {"secs": 2, "nanos": 10}],
+            "budgets": {"build": {"secs": 600, "nanos": 0}},
+            "windows": [{"start": 1, "end": 5}, null]
+        }
+    "#;
+    let schedule: Schedule = serde_json::from_str(j).unwrap();
+    println!("{:?}", schedule);
+    println!("{}", serde_json::to_string(&schedule).unwrap());
+
+    let j = r#"{"laps": [], "budgets": {}, "windows": []}"#;
+    let schedule: Schedule = serde_json::from_str(j).unwrap();
+    assert!(schedule.deadline.is_none());
+
+    // Inconsistent fields are rejected before they reach `other_crate`. A
+    // zero on either side goes with any sign.
+    for j in &[
+        r#"{"secs": -3, "nanos": -500}"#,
+        r#"{"secs": 0, "nanos": -500}"#,
+        r#"{"secs": -3, "nanos": 0}"#,
+    ] {
+        let dur = serde_json::from_str(j).map(|a: Helper| a.0);
+        println!("{} => {:?}", j, dur);
+        assert!(dur.is_ok(), "{}", j);
+    }
+    for j in &[
+        r#"{"secs": 3, "nanos": -500}"#,
+        r#"{"secs": -3, "nanos": 500}"#,
+        r#"{"secs": 0, "nanos": 1000000000}"#,
+        r#"{"secs": 0, "nanos": -1000000000}"#,
+    ] {
+        let dur = serde_json::from_str(j).map(|a: Helper| a.0);
+        println!("{} => {:?}", j, dur.as_ref().map_err(|err| err.to_string()));
+        assert!(dur.is_err(), "{}", j);
+    }
+
+    // Stored records from every schema generation read as the current
+    // `Process`, and are written back as version 4.
+    for j in &[
+        r#"{"command_line": "cargo build", "wall_time": 1.5}"#,
+        r#"{"version": 2, "command_line": "cargo test", "wall_time": "-0.000001321"}"#,
+        r#"{"version": 3, "command": ["cargo", "run"], "wall_time": {"secs": 3, "nanos": 7}}"#,
+        r#"{"version": 4, "command_line": "cargo fmt", "wall_time": {"secs": 4, "nanos": 0}}"#,
+        r#"{"version": 5, "command_line": "cargo fmt", "wall_time": {"secs": 4, "nanos": 0}}"#,
+    ] {
+        match serde_json::from_str::<Versioned<Process>>(j) {
+            Ok(process) => println!("{}", serde_json::to_string(&process).unwrap()),
+            Err(err) => println!("{}", err),
+        }
+    }
+
+    // Migrating never changes the command line of an older record.
+    let j = r#"{"command_line": "sh -c \"echo  hi\"", "wall_time": 0.25}"#;
+    let process = serde_json::from_str::<Versioned<Process>>(j).unwrap().0;
+    assert_eq!(process.command_line, r#"sh -c "echo  hi""#);
+    let j = r#"{"version": 2, "command_line": "a\tb  c", "wall_time": "1"}"#;
+    let process = serde_json::from_str::<Versioned<Process>>(j).unwrap().0;
+    assert_eq!(process.command_line, "a\tb  c");
+
+    // Version 3 arguments are quoted where joining them would be ambiguous.
+    let j = r#"{"version": 3, "command": ["sh", "-c", "echo  'hi'", ""], "wall_time": {"secs": 1, "nanos": 0}}"#;
+    let process = serde_json::from_str::<Versioned<Process>>(j).unwrap().0;
+    assert_eq!(process.command_line, r#"sh -c 'echo  '\''hi'\''' ''"#);
 }
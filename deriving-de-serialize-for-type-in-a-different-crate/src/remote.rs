@@ -0,0 +1,623 @@
+//! Shims for foreign types
+//!
+//! Every shim is a module that can be named in `#[serde(with = "...")]`. When
+//! the foreign type can be described by a remote definition, the module also
+//! holds that definition, so `remote::duration::DurationDef` works with
+//! `with` as well.
+//!
+//! Remote definitions also implement `SerializeAs` and `DeserializeAs`, which
+//! lets `OptionOf`, `VecOf` and `HashMapOf` wrap any of them, e.g.
+//! `#[serde(default, with = "remote::OptionOf::<DurationDef>")]` for an
+//! `Option<Duration>`. Serde parses the `with` path as an expression, so the
+//! type argument needs the turbofish.
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::hash::Hash;
+use std::marker::PhantomData;
+use std::net::{IpAddr, SocketAddr};
+use std::num;
+use std::ops::Range;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::de::{self, Unexpected};
+use serde::ser;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// A type that is able to serialize a `T` on its behalf, such as the remote
+/// definition of `T`.
+pub trait SerializeAs<T: ?Sized> {
+    fn serialize_as<S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer;
+}
+
+/// A type that is able to deserialize a `T` on its behalf, such as the
+/// remote definition of `T`.
+pub trait DeserializeAs<'de, T>: Sized {
+    fn deserialize_as<D>(deserializer: D) -> Result<T, D::Error>
+    where
+        D: Deserializer<'de>;
+}
+
+/// Implements `SerializeAs` and `DeserializeAs` for a remote definition by
+/// forwarding to the `serialize` and `deserialize` functions that
+/// `#[serde(remote = "...")]` generates.
+macro_rules! remote_def {
+    ($def:ty => $remote:ty) => {
+        impl $crate::remote::SerializeAs<$remote> for $def {
+            fn serialize_as<S>(value: &$remote, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: serde::Serializer,
+            {
+                <$def>::serialize(value, serializer)
+            }
+        }
+
+        impl<'de> $crate::remote::DeserializeAs<'de, $remote> for $def {
+            fn deserialize_as<D>(deserializer: D) -> Result<$remote, D::Error>
+            where
+                D: serde::Deserializer<'de>,
+            {
+                <$def>::deserialize(deserializer)
+            }
+        }
+    };
+}
+
+pub(crate) use remote_def;
+
+/// The reasons the fields of a `Duration` or `SystemTime` do not make one.
+#[derive(Debug)]
+pub enum TimeError {
+    /// `nanos` is a whole second or more.
+    NanosOutOfRange(u32),
+    /// The time is too far from the epoch for this platform's `SystemTime`.
+    OutOfRange { secs: i64, nanos: u32 },
+}
+
+impl fmt::Display for TimeError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TimeError::NanosOutOfRange(nanos) => write!(
+                formatter,
+                "nanos must be less than 1000000000, got {}",
+                nanos
+            ),
+            TimeError::OutOfRange { secs, nanos } => write!(
+                formatter,
+                "{}s {}ns from the epoch is not a representable time",
+                secs, nanos
+            ),
+        }
+    }
+}
+
+/// `std::time::Duration` as `{"secs": u64, "nanos": u32}`, where `nanos` is
+/// less than a second.
+pub mod duration {
+    use super::*;
+
+    /// Deserializes the unchecked fields as a `RawDuration`, since
+    /// `Duration::new` panics when `nanos` carries into a `secs` that is
+    /// already at its maximum.
+    #[derive(Serialize, Deserialize)]
+    #[serde(remote = "Duration", try_from = "RawDuration")]
+    pub struct DurationDef {
+        #[serde(getter = "Duration::as_secs")]
+        secs: u64,
+        #[serde(getter = "Duration::subsec_nanos")]
+        nanos: u32,
+    }
+
+    remote_def!(DurationDef => Duration);
+
+    /// The fields of a `Duration` as they appear in the input, before they
+    /// are checked.
+    #[derive(Deserialize)]
+    pub struct RawDuration {
+        secs: u64,
+        nanos: u32,
+    }
+
+    impl TryFrom<RawDuration> for Duration {
+        type Error = TimeError;
+
+        fn try_from(raw: RawDuration) -> Result<Duration, TimeError> {
+            if raw.nanos >= 1_000_000_000 {
+                return Err(TimeError::NanosOutOfRange(raw.nanos));
+            }
+            Ok(Duration::new(raw.secs, raw.nanos))
+        }
+    }
+
+    pub fn serialize<S>(value: &Duration, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        DurationDef::serialize(value, serializer)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Duration, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        DurationDef::deserialize(deserializer)
+    }
+}
+
+/// `std::time::SystemTime` as the offset from the Unix epoch, `{"secs": i64,
+/// "nanos": u32}`. Times before the epoch have negative `secs`, and `nanos`
+/// always counts forward, so one nanosecond before the epoch is
+/// `{"secs": -1, "nanos": 999999999}`.
+pub mod system_time {
+    use super::*;
+
+    /// Deserializes the unchecked fields as a `RawSystemTime`, since the
+    /// range of `SystemTime` depends on the platform. Serializing goes through
+    /// `RawSystemTime` as well, because a getter cannot report a time whose
+    /// offset does not fit in `secs`.
+    #[derive(Deserialize)]
+    #[serde(remote = "SystemTime", try_from = "RawSystemTime")]
+    pub struct SystemTimeDef {
+        secs: i64,
+        nanos: u32,
+    }
+
+    impl SystemTimeDef {
+        pub fn serialize<S>(value: &SystemTime, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            unix_offset(value)
+                .ok_or_else(|| {
+                    ser::Error::custom(format!(
+                        "{:?} is too far from the epoch for an i64 of seconds",
+                        value
+                    ))
+                })?
+                .serialize(serializer)
+        }
+    }
+
+    /// Splits a `SystemTime` into whole seconds and forward counting
+    /// nanoseconds relative to the epoch, or `None` if the seconds do not fit
+    /// in an `i64`.
+    fn unix_offset(time: &SystemTime) -> Option<RawSystemTime> {
+        let (secs, nanos) = match time.duration_since(UNIX_EPOCH) {
+            Ok(after) => (i128::from(after.as_secs()), after.subsec_nanos()),
+            Err(err) => {
+                let before = err.duration();
+                match before.subsec_nanos() {
+                    0 => (-i128::from(before.as_secs()), 0),
+                    nanos => (-i128::from(before.as_secs()) - 1, 1_000_000_000 - nanos),
+                }
+            }
+        };
+        let secs = i64::try_from(secs).ok()?;
+        Some(RawSystemTime { secs, nanos })
+    }
+
+    remote_def!(SystemTimeDef => SystemTime);
+
+    /// The fields of a `SystemTime` as they appear in the input, before they
+    /// are checked.
+    #[derive(Serialize, Deserialize)]
+    pub struct RawSystemTime {
+        secs: i64,
+        nanos: u32,
+    }
+
+    impl TryFrom<RawSystemTime> for SystemTime {
+        type Error = TimeError;
+
+        fn try_from(raw: RawSystemTime) -> Result<SystemTime, TimeError> {
+            if raw.nanos >= 1_000_000_000 {
+                return Err(TimeError::NanosOutOfRange(raw.nanos));
+            }
+            let secs = Duration::from_secs(raw.secs.unsigned_abs());
+            let whole = if raw.secs >= 0 {
+                UNIX_EPOCH.checked_add(secs)
+            } else {
+                UNIX_EPOCH.checked_sub(secs)
+            };
+            whole
+                .and_then(|whole| whole.checked_add(Duration::from_nanos(u64::from(raw.nanos))))
+                .ok_or(TimeError::OutOfRange {
+                    secs: raw.secs,
+                    nanos: raw.nanos,
+                })
+        }
+    }
+
+    pub fn serialize<S>(value: &SystemTime, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        SystemTimeDef::serialize(value, serializer)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<SystemTime, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        SystemTimeDef::deserialize(deserializer)
+    }
+}
+
+/// `std::net::SocketAddr` as `{"ip": "127.0.0.1", "port": 8080}`.
+pub mod socket_addr {
+    use super::*;
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(remote = "SocketAddr")]
+    pub struct SocketAddrDef {
+        #[serde(getter = "SocketAddr::ip")]
+        ip: IpAddr,
+        #[serde(getter = "SocketAddr::port")]
+        port: u16,
+    }
+
+    remote_def!(SocketAddrDef => SocketAddr);
+
+    impl From<SocketAddrDef> for SocketAddr {
+        fn from(def: SocketAddrDef) -> SocketAddr {
+            SocketAddr::new(def.ip, def.port)
+        }
+    }
+
+    pub fn serialize<S>(value: &SocketAddr, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        SocketAddrDef::serialize(value, serializer)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<SocketAddr, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        SocketAddrDef::deserialize(deserializer)
+    }
+}
+
+/// `std::net::SocketAddr` as `"127.0.0.1:8080"` or `"[::1]:8080"`.
+pub mod socket_addr_str {
+    use super::*;
+
+    pub fn serialize<S>(value: &SocketAddr, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(value)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<SocketAddr, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s: String = Deserialize::deserialize(deserializer)?;
+        s.parse()
+            .map_err(|_| de::Error::invalid_value(Unexpected::Str(&s), &"a socket address"))
+    }
+}
+
+/// `std::net::SocketAddr` as `["127.0.0.1", 8080]`.
+pub mod socket_addr_tuple {
+    use super::*;
+
+    pub fn serialize<S>(value: &SocketAddr, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        (value.ip(), value.port()).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<SocketAddr, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        <(IpAddr, u16)>::deserialize(deserializer).map(SocketAddr::from)
+    }
+}
+
+/// Any of the `std::num::NonZero*` types, as the plain integer. Zero is
+/// rejected when deserializing.
+pub mod non_zero {
+    use super::*;
+
+    /// A `NonZero*` type and the primitive it wraps.
+    pub trait NonZero: Sized {
+        type Primitive;
+
+        fn new(value: Self::Primitive) -> Option<Self>;
+        fn get(&self) -> Self::Primitive;
+    }
+
+    macro_rules! non_zero {
+        ($($ty:ident => $primitive:ident,)*) => {
+            $(
+                impl NonZero for num::$ty {
+                    type Primitive = $primitive;
+
+                    fn new(value: $primitive) -> Option<Self> {
+                        num::$ty::new(value)
+                    }
+
+                    fn get(&self) -> $primitive {
+                        num::$ty::get(*self)
+                    }
+                }
+            )*
+        };
+    }
+
+    non_zero! {
+        NonZeroI8 => i8,
+        NonZeroI16 => i16,
+        NonZeroI32 => i32,
+        NonZeroI64 => i64,
+        NonZeroI128 => i128,
+        NonZeroIsize => isize,
+        NonZeroU8 => u8,
+        NonZeroU16 => u16,
+        NonZeroU32 => u32,
+        NonZeroU64 => u64,
+        NonZeroU128 => u128,
+        NonZeroUsize => usize,
+    }
+
+    pub fn serialize<N, S>(value: &N, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        N: NonZero,
+        N::Primitive: Serialize,
+        S: Serializer,
+    {
+        value.get().serialize(serializer)
+    }
+
+    pub fn deserialize<'de, N, D>(deserializer: D) -> Result<N, D::Error>
+    where
+        N: NonZero,
+        N::Primitive: Deserialize<'de>,
+        D: Deserializer<'de>,
+    {
+        let value = N::Primitive::deserialize(deserializer)?;
+        N::new(value)
+            .ok_or_else(|| de::Error::invalid_value(Unexpected::Unsigned(0), &"a non-zero integer"))
+    }
+}
+
+/// `std::ops::Range<Idx>` as `{"start": ..., "end": ...}`.
+pub mod range {
+    use super::*;
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(remote = "Range")]
+    pub struct RangeDef<Idx> {
+        start: Idx,
+        end: Idx,
+    }
+
+    impl<Idx: Serialize> SerializeAs<Range<Idx>> for RangeDef<Idx> {
+        fn serialize_as<S>(value: &Range<Idx>, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            RangeDef::serialize(value, serializer)
+        }
+    }
+
+    impl<'de, Idx: Deserialize<'de>> DeserializeAs<'de, Range<Idx>> for RangeDef<Idx> {
+        fn deserialize_as<D>(deserializer: D) -> Result<Range<Idx>, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            RangeDef::deserialize(deserializer)
+        }
+    }
+
+    impl<Idx> From<RangeDef<Idx>> for Range<Idx> {
+        fn from(def: RangeDef<Idx>) -> Range<Idx> {
+            def.start..def.end
+        }
+    }
+
+    pub fn serialize<Idx, S>(value: &Range<Idx>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        Idx: Serialize,
+        S: Serializer,
+    {
+        RangeDef::serialize(value, serializer)
+    }
+
+    pub fn deserialize<'de, Idx, D>(deserializer: D) -> Result<Range<Idx>, D::Error>
+    where
+        Idx: Deserialize<'de>,
+        D: Deserializer<'de>,
+    {
+        RangeDef::deserialize(deserializer)
+    }
+}
+
+/// Serializes the `T` it points to through `R`.
+struct SerializeWith<'a, T: ?Sized, R>(&'a T, PhantomData<R>);
+
+impl<'a, T: ?Sized, R> SerializeWith<'a, T, R> {
+    fn new(value: &'a T) -> Self {
+        SerializeWith(value, PhantomData)
+    }
+}
+
+impl<'a, T: ?Sized, R: SerializeAs<T>> Serialize for SerializeWith<'a, T, R> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        R::serialize_as(self.0, serializer)
+    }
+}
+
+/// Deserializes a `T` through `R`.
+struct DeserializeWith<T, R>(T, PhantomData<R>);
+
+impl<'de, T, R: DeserializeAs<'de, T>> Deserialize<'de> for DeserializeWith<T, R> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        R::deserialize_as(deserializer).map(|value| DeserializeWith(value, PhantomData))
+    }
+}
+
+/// `Option<T>`, where `Some` goes through the remote definition `R` of `T`.
+///
+/// Serde only treats a missing `Option` field as `None` when the field has
+/// no `with`, so a field using `OptionOf` also needs `#[serde(default)]`:
+///
+/// ```ignore
+/// #[serde(default, with = "remote::OptionOf::<DurationDef>")]
+/// deadline: Option<Duration>,
+/// ```
+pub struct OptionOf<R>(PhantomData<R>);
+
+impl<R> OptionOf<R> {
+    pub fn serialize<T, S>(value: &Option<T>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        R: SerializeAs<T>,
+        S: Serializer,
+    {
+        Self::serialize_as(value, serializer)
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+    where
+        R: DeserializeAs<'de, T>,
+        D: Deserializer<'de>,
+    {
+        Self::deserialize_as(deserializer)
+    }
+}
+
+impl<T, R: SerializeAs<T>> SerializeAs<Option<T>> for OptionOf<R> {
+    fn serialize_as<S>(value: &Option<T>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match value {
+            Some(value) => serializer.serialize_some(&SerializeWith::<T, R>::new(value)),
+            None => serializer.serialize_none(),
+        }
+    }
+}
+
+impl<'de, T, R: DeserializeAs<'de, T>> DeserializeAs<'de, Option<T>> for OptionOf<R> {
+    fn deserialize_as<D>(deserializer: D) -> Result<Option<T>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value: Option<DeserializeWith<T, R>> = Deserialize::deserialize(deserializer)?;
+        Ok(value.map(|value| value.0))
+    }
+}
+
+/// `Vec<T>`, where every element goes through the remote definition `R` of
+/// `T`.
+pub struct VecOf<R>(PhantomData<R>);
+
+impl<R> VecOf<R> {
+    pub fn serialize<T, S>(values: &[T], serializer: S) -> Result<S::Ok, S::Error>
+    where
+        R: SerializeAs<T>,
+        S: Serializer,
+    {
+        Self::serialize_as(values, serializer)
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<Vec<T>, D::Error>
+    where
+        R: DeserializeAs<'de, T>,
+        D: Deserializer<'de>,
+    {
+        Self::deserialize_as(deserializer)
+    }
+}
+
+impl<T, R: SerializeAs<T>> SerializeAs<[T]> for VecOf<R> {
+    fn serialize_as<S>(values: &[T], serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_seq(values.iter().map(SerializeWith::<T, R>::new))
+    }
+}
+
+impl<T, R: SerializeAs<T>> SerializeAs<Vec<T>> for VecOf<R> {
+    fn serialize_as<S>(values: &Vec<T>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        Self::serialize_as(values.as_slice(), serializer)
+    }
+}
+
+impl<'de, T, R: DeserializeAs<'de, T>> DeserializeAs<'de, Vec<T>> for VecOf<R> {
+    fn deserialize_as<D>(deserializer: D) -> Result<Vec<T>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let values: Vec<DeserializeWith<T, R>> = Deserialize::deserialize(deserializer)?;
+        Ok(values.into_iter().map(|value| value.0).collect())
+    }
+}
+
+/// `HashMap<K, T>`, where every value goes through the remote definition `R`
+/// of `T`. The keys use their own `Serialize` and `Deserialize` impls.
+pub struct HashMapOf<R>(PhantomData<R>);
+
+impl<R> HashMapOf<R> {
+    pub fn serialize<K, T, S>(map: &HashMap<K, T>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        K: Serialize,
+        R: SerializeAs<T>,
+        S: Serializer,
+    {
+        Self::serialize_as(map, serializer)
+    }
+
+    pub fn deserialize<'de, K, T, D>(deserializer: D) -> Result<HashMap<K, T>, D::Error>
+    where
+        K: Deserialize<'de> + Eq + Hash,
+        R: DeserializeAs<'de, T>,
+        D: Deserializer<'de>,
+    {
+        Self::deserialize_as(deserializer)
+    }
+}
+
+impl<K: Serialize, T, R: SerializeAs<T>> SerializeAs<HashMap<K, T>> for HashMapOf<R> {
+    fn serialize_as<S>(map: &HashMap<K, T>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_map(
+            map.iter()
+                .map(|(key, value)| (key, SerializeWith::<T, R>::new(value))),
+        )
+    }
+}
+
+impl<'de, K, T, R> DeserializeAs<'de, HashMap<K, T>> for HashMapOf<R>
+where
+    K: Deserialize<'de> + Eq + Hash,
+    R: DeserializeAs<'de, T>,
+{
+    fn deserialize_as<D>(deserializer: D) -> Result<HashMap<K, T>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let map: HashMap<K, DeserializeWith<T, R>> = Deserialize::deserialize(deserializer)?;
+        Ok(map.into_iter().map(|(key, value)| (key, value.0)).collect())
+    }
+}
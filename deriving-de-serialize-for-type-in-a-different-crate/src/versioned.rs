@@ -0,0 +1,71 @@
+//! Versioned wire formats
+//!
+//! A `Versioned<T>` payload carries a `"version"` field next to the fields of
+//! `T`. Serializing always writes the current version of `T`, while
+//! deserializing accepts any version `T` knows how to migrate from.
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+/// A type whose wire format has changed over time.
+pub trait Schema: Serialize + Sized {
+    /// The version that is written when serializing.
+    const VERSION: u64;
+
+    /// Reads a payload written as `version`, migrating it step by step into
+    /// the current shape. The payload still contains the `"version"` field.
+    fn migrate(version: u64, payload: serde_json::Value) -> Result<Self, serde_json::Error>;
+}
+
+/// A `T` together with the version of its wire format.
+///
+/// The version has to be known before the rest of the payload can be
+/// interpreted, and it may come after the other fields, so the payload is
+/// buffered as a `serde_json::Value` first. A payload without a `"version"`
+/// field predates versioning and is read as version 1.
+#[derive(Debug)]
+pub struct Versioned<T>(pub T);
+
+impl<T: Schema> Serialize for Versioned<T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        #[derive(Serialize)]
+        struct Latest<'a, T> {
+            version: u64,
+            #[serde(flatten)]
+            value: &'a T,
+        }
+
+        Latest {
+            version: T::VERSION,
+            value: &self.0,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de, T: Schema> Deserialize<'de> for Versioned<T> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let payload = serde_json::Value::deserialize(deserializer)?;
+        let version = match payload.get("version") {
+            None => 1,
+            Some(version) => version.as_u64().ok_or_else(|| {
+                de::Error::custom(format!("version must be an integer, got {}", version))
+            })?,
+        };
+        if version == 0 || version > T::VERSION {
+            return Err(de::Error::custom(format!(
+                "unsupported version {}, expected 1 to {}",
+                version,
+                T::VERSION
+            )));
+        }
+        T::migrate(version, payload)
+            .map(Versioned)
+            .map_err(de::Error::custom)
+    }
+}
@@ -0,0 +1,48 @@
+//! Reads a message from standard input and prints which enum representation
+//! it uses. Given a representation as argument, it prints the message
+//! transcoded into that representation instead.
+//!
+//! ```text
+//! $ echo '{"t": "Request", "c": {...}}' | detect-representation
+//! adjacently-tagged
+//! $ echo '{"t": "Request", "c": {...}}' | detect-representation default
+//! {"Request":{...}}
+//! ```
+
+use std::io::{self, Read};
+use std::process;
+
+use enum_representations::convert::{self, Representation};
+
+fn run() -> Result<(), String> {
+    let target = match std::env::args().nth(1) {
+        Some(name) => Some(name.parse::<Representation>()?),
+        None => None,
+    };
+
+    let mut input = String::new();
+    io::stdin()
+        .read_to_string(&mut input)
+        .map_err(|err| format!("cannot read standard input: {}", err))?;
+    let json: serde_json::Value =
+        serde_json::from_str(&input).map_err(|err| format!("invalid JSON: {}", err))?;
+    let detected = Representation::detect(&json)
+        .ok_or_else(|| "not a message: expected a JSON object".to_owned())?;
+
+    match target {
+        None => println!("{}", detected),
+        Some(target) => {
+            let output = convert::transcode(&input, detected, target)
+                .map_err(|err| format!("invalid {} message: {}", detected, err))?;
+            println!("{}", output);
+        }
+    }
+    Ok(())
+}
+
+fn main() {
+    if let Err(err) = run() {
+        eprintln!("detect-representation: {}", err);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,151 @@
+//! # Borrowed messages
+//!
+//! The `Message*` enums own their strings, so every message read costs an
+//! allocation per string. The enums here borrow them from the input
+//! instead. Their strings are `Cow<'a, str>` marked `#[serde(borrow)]`,
+//! which borrows a string as it appears in the input and only allocates
+//! when it contains an escape sequence that has to be decoded. A plain
+//! `&'a str` would fail to deserialize such a string.
+//!
+//! `Params` is shared with the owned enums. It holds no strings, and the
+//! integers in `field_3` have to be parsed into a `Vec` either way. Unlike
+//! the owned enums, these have no `Unknown` variant.
+//!
+//! A derived internally tagged or untagged `Deserialize` first buffers the
+//! whole object, because the tag may come after the fields it selects.
+//! `MessageInternallyTagged` and `MessageUntagged` avoid that by borrowing
+//! the object as a `&RawValue`, reading only the tag or the keys they need
+//! from it, and then reading the chosen variant straight from the same
+//! text. Borrowing needs the input to outlive the message, so these enums
+//! can only be read by `serde_json::from_str` and `serde_json::from_slice`.
+
+use std::borrow::Cow;
+
+use serde::de::{self, IgnoredAny};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::value::RawValue;
+
+use crate::Params;
+
+/// The variants of the tagged enums.
+const VARIANTS: &[&str] = &["Request", "Response"];
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Request<'a> {
+    #[serde(borrow)]
+    pub id: Cow<'a, str>,
+    #[serde(borrow)]
+    pub method: Cow<'a, str>,
+    pub params: Params,
+}
+
+/// A response whose `result` is kept as the raw JSON text it was read from,
+/// until it can be decoded into the type the request's method returns.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Response<'a, R = &'a RawValue> {
+    #[serde(borrow)]
+    pub id: Cow<'a, str>,
+    pub result: R,
+}
+
+/// The `Request` of `MessageUntagged`, which also has a `tuple`.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct UntaggedRequest<'a> {
+    #[serde(borrow)]
+    pub id: Cow<'a, str>,
+    #[serde(borrow)]
+    pub method: Cow<'a, str>,
+    pub tuple: (u32, u32),
+    pub params: Params,
+}
+
+/// Externally tagged. The tag comes first, so nothing is buffered.
+#[derive(Serialize, Deserialize, Debug)]
+pub enum MessageDefault<'a, R = &'a RawValue> {
+    Request(#[serde(borrow)] Request<'a>),
+    Response(#[serde(borrow)] Response<'a, R>),
+}
+
+/// Internally tagged, with `Deserialize` implemented below.
+#[derive(Serialize, Debug)]
+#[serde(tag = "type")]
+pub enum MessageInternallyTagged<'a, R = &'a RawValue> {
+    Request(Request<'a>),
+    Response(Response<'a, R>),
+}
+
+/// Adjacently tagged. The content is only buffered if it comes before the
+/// tag.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "t", content = "c")]
+pub enum MessageAdjacentlyTagged<'a, R = &'a RawValue> {
+    Request(#[serde(borrow)] Request<'a>),
+    Response(#[serde(borrow)] Response<'a, R>),
+}
+
+/// Untagged, with `Deserialize` implemented below. As with the owned
+/// `MessageUntagged`, a `method` means `Request` and a `result` means
+/// `Response`.
+#[derive(Serialize, Debug)]
+#[serde(untagged)]
+pub enum MessageUntagged<'a, R = &'a RawValue> {
+    Request(UntaggedRequest<'a>),
+    Response(Response<'a, R>),
+}
+
+/// Reads the variant `name` from `raw`, which is borrowed from the input.
+fn variant<'de, T, E>(name: &str, raw: &'de RawValue) -> Result<T, E>
+where
+    T: Deserialize<'de>,
+    E: de::Error,
+{
+    serde_json::from_str(raw.get()).map_err(|err| E::custom(format!("invalid {}: {}", name, err)))
+}
+
+impl<'de: 'a, 'a, R: Deserialize<'de>> Deserialize<'de> for MessageInternallyTagged<'a, R> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        /// Only the tag, skipping every other member.
+        #[derive(Deserialize)]
+        struct Tag<'a> {
+            #[serde(borrow, rename = "type")]
+            tag: Cow<'a, str>,
+        }
+
+        let raw = <&RawValue>::deserialize(deserializer)?;
+        let Tag { tag } = serde_json::from_str(raw.get()).map_err(de::Error::custom)?;
+        match &*tag {
+            "Request" => variant(&tag, raw).map(MessageInternallyTagged::Request),
+            "Response" => variant(&tag, raw).map(MessageInternallyTagged::Response),
+            _ => Err(de::Error::unknown_variant(&tag, VARIANTS)),
+        }
+    }
+}
+
+impl<'de: 'a, 'a, R: Deserialize<'de>> Deserialize<'de> for MessageUntagged<'a, R> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        /// Which of the keys that pick a variant are present.
+        #[derive(Deserialize)]
+        struct Keys {
+            method: Option<IgnoredAny>,
+            result: Option<IgnoredAny>,
+        }
+
+        let raw = <&RawValue>::deserialize(deserializer)?;
+        let keys: Keys = serde_json::from_str(raw.get()).map_err(de::Error::custom)?;
+        if keys.method.is_some() {
+            variant("Request", raw).map(MessageUntagged::Request)
+        } else if keys.result.is_some() {
+            variant("Response", raw).map(MessageUntagged::Response)
+        } else {
+            Err(de::Error::custom(
+                "expected an object with `method` or `result`",
+            ))
+        }
+    }
+}
@@ -0,0 +1,57 @@
+//! # Dispatch by key presence
+//!
+//! `#[serde(untagged)]` buffers the input and tries every variant in turn.
+//! When none of them fits, the only error left is "data did not match any
+//! variant of untagged enum", which hides why the intended variant failed.
+//!
+//! Often a variant can be recognized by a key that no other variant has.
+//! `deserialize` picks the variant from the keys that are present, and then
+//! deserializes only that variant, so its own error is the one reported.
+//!
+//! `MessageUntagged` uses it from its `Deserialize` impl. Any other enum can
+//! do the same by calling `by_key::deserialize` with its own keys and a
+//! remote definition of itself.
+
+use serde::de::{self, Deserialize, Deserializer};
+use serde_json::{Map, Value};
+
+/// Deserializes an object as the variant named by the first of `keys` that
+/// it contains. `keys` pairs a key with the name of the variant it selects.
+///
+/// `externally_tagged` deserializes the enum from its externally tagged
+/// form, `{"Variant": {...}}`. The usual way to get one without changing the
+/// enum is a remote definition of it, e.g.
+/// `#[derive(Deserialize)] #[serde(remote = "MessageUntagged")]`.
+pub fn deserialize<'de, D, T, F>(
+    deserializer: D,
+    keys: &[(&str, &'static str)],
+    externally_tagged: F,
+) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    F: FnOnce(Value) -> Result<T, serde_json::Error>,
+{
+    let object = match Value::deserialize(deserializer)? {
+        Value::Object(object) => object,
+        other => {
+            return Err(de::Error::custom(format!(
+                "invalid type: {}, expected an object",
+                other
+            )))
+        }
+    };
+
+    let variant = keys
+        .iter()
+        .find(|(key, _)| object.contains_key(*key))
+        .map(|(_, variant)| *variant)
+        .ok_or_else(|| {
+            let keys: Vec<_> = keys.iter().map(|(key, _)| format!("`{}`", key)).collect();
+            de::Error::custom(format!("expected an object with {}", keys.join(" or ")))
+        })?;
+
+    let mut tagged = Map::new();
+    tagged.insert(variant.to_owned(), Value::Object(object));
+    externally_tagged(Value::Object(tagged))
+        .map_err(|err| de::Error::custom(format!("invalid {}: {}", variant, err)))
+}
@@ -0,0 +1,402 @@
+//! # Converting between representations
+//!
+//! The four `Message*` enums hold the same messages, so a message read in one
+//! representation can be written in any other. `MessageDefault` is the hub:
+//! every other enum converts from and into it, and the remaining conversions
+//! go through it.
+//!
+//! Two things do not carry over exactly. `MessageUntagged::Request` has a
+//! `tuple` that the other enums lack, which is dropped when leaving
+//! `MessageUntagged` and is `(0, 0)` when entering it. An `Unknown` message
+//! keeps its content, which is rewritten to fit the new representation.
+//!
+//! That rewrite cannot always be undone. Internally tagged content that is
+//! not an object is kept under `"content"` next to the `"type"`, and reads
+//! back as that object. Untagged content is written as it is, and reads
+//! back as an `Unknown` only if it is an object with none of the known
+//! fields. The `From` conversions make these messages anyway, while
+//! `transcode` refuses them, as well as any request that would lose its
+//! `tuple` or be given one.
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::de::{MapAccess, Visitor};
+use serde::ser;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::value::RawValue;
+use serde_json::Value;
+
+use crate::{MessageAdjacentlyTagged, MessageDefault, MessageInternallyTagged, MessageUntagged};
+
+/// The tag given to an untagged `Unknown` message, which has none of its own.
+const UNTAGGED: &str = "Unknown";
+
+/// One of the ways the `Message*` enums are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Representation {
+    Default,
+    InternallyTagged,
+    AdjacentlyTagged,
+    Untagged,
+}
+
+impl Representation {
+    pub const ALL: [Representation; 4] = [
+        Representation::Default,
+        Representation::InternallyTagged,
+        Representation::AdjacentlyTagged,
+        Representation::Untagged,
+    ];
+
+    /// The name used on the command line.
+    pub fn name(self) -> &'static str {
+        match self {
+            Representation::Default => "default",
+            Representation::InternallyTagged => "internally-tagged",
+            Representation::AdjacentlyTagged => "adjacently-tagged",
+            Representation::Untagged => "untagged",
+        }
+    }
+
+    /// Guesses the representation of a message from the shape of its JSON. A
+    /// string `"type"` means internally tagged, a string `"t"` with at most a
+    /// `"c"` next to it means adjacently tagged, and a single member holding
+    /// an object means externally tagged. Any other object is untagged, and
+    /// anything that is not an object is `None`.
+    ///
+    /// Only the shape is looked at. Whether the message is valid in that
+    /// representation is found out by deserializing it.
+    pub fn detect(json: &serde_json::Value) -> Option<Representation> {
+        let object = json.as_object()?;
+        let is_string = |key| object.get(key).is_some_and(serde_json::Value::is_string);
+        if is_string("type") {
+            Some(Representation::InternallyTagged)
+        } else if is_string("t") && object.keys().all(|key| key == "t" || key == "c") {
+            Some(Representation::AdjacentlyTagged)
+        } else if object.len() == 1 && object.values().all(serde_json::Value::is_object) {
+            Some(Representation::Default)
+        } else {
+            Some(Representation::Untagged)
+        }
+    }
+}
+
+impl fmt::Display for Representation {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str(self.name())
+    }
+}
+
+impl FromStr for Representation {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Representation::ALL
+            .iter()
+            .cloned()
+            .find(|representation| representation.name() == name)
+            .ok_or_else(|| {
+                let names: Vec<_> = Representation::ALL.iter().map(|r| r.name()).collect();
+                format!(
+                    "unknown representation `{}`, expected one of {}",
+                    name,
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+/// Reads `json` as a message written `from` one representation, and writes it
+/// in the `to` representation.
+///
+/// An `Unknown` message is only written if it reads back as the same
+/// message, tag aside for `Untagged`, which has none. Content that is not an
+/// object cannot carry a `"type"`, and an untagged object with a field of a
+/// known variant reads back as that variant, so these fail instead of
+/// producing JSON that the `to` enum cannot read.
+///
+/// Only `Untagged` has a `tuple` in its requests. A request fails to leave
+/// `Untagged` unless its `tuple` is `(0, 0)`, and fails to enter it from any
+/// other representation, which has no `tuple` to give it.
+pub fn transcode(
+    json: &str,
+    from: Representation,
+    to: Representation,
+) -> Result<String, serde_json::Error> {
+    let message = match from {
+        Representation::Untagged => {
+            let message: MessageUntagged = serde_json::from_str(json)?;
+            if to == Representation::Untagged {
+                return serde_json::to_string(&message);
+            }
+            if let MessageUntagged::Request { tuple, .. } = message {
+                if tuple != (0, 0) {
+                    return Err(ser::Error::custom(format!(
+                        "request with tuple {:?} cannot be written {}",
+                        tuple, to
+                    )));
+                }
+            }
+            message.into()
+        }
+        _ => read(json, from)?,
+    };
+    if let (MessageDefault::Request { .. }, Representation::Untagged) = (&message, to) {
+        return Err(ser::Error::custom(format!(
+            "request read {} has no tuple to be written {}",
+            from, to
+        )));
+    }
+    let unknown = match &message {
+        MessageDefault::Unknown { tag, raw } => Some((tag.clone(), raw.clone())),
+        _ => None,
+    };
+    let written = write(message, to)?;
+    if let Some((tag, raw)) = unknown {
+        let reversible = match read(&written, to) {
+            Ok(MessageDefault::Unknown {
+                tag: reread_tag,
+                raw: reread,
+            }) => {
+                (to == Representation::Untagged || reread_tag == tag)
+                    && serde_json::from_str::<Value>(reread.get())?
+                        == serde_json::from_str::<Value>(raw.get())?
+            }
+            _ => false,
+        };
+        if !reversible {
+            return Err(ser::Error::custom(format!(
+                "{} message with content {} cannot be written {}",
+                tag, raw, to
+            )));
+        }
+    }
+    Ok(written)
+}
+
+/// Reads `json` as a message in the `from` representation.
+fn read(json: &str, from: Representation) -> Result<MessageDefault, serde_json::Error> {
+    Ok(match from {
+        Representation::Default => serde_json::from_str(json)?,
+        Representation::InternallyTagged => {
+            serde_json::from_str::<MessageInternallyTagged>(json)?.into()
+        }
+        Representation::AdjacentlyTagged => {
+            serde_json::from_str::<MessageAdjacentlyTagged>(json)?.into()
+        }
+        Representation::Untagged => serde_json::from_str::<MessageUntagged>(json)?.into(),
+    })
+}
+
+/// Writes `message` in the `to` representation.
+fn write(message: MessageDefault, to: Representation) -> Result<String, serde_json::Error> {
+    match to {
+        Representation::Default => serde_json::to_string(&message),
+        Representation::InternallyTagged => {
+            serde_json::to_string(&MessageInternallyTagged::from(message))
+        }
+        Representation::AdjacentlyTagged => {
+            serde_json::to_string(&MessageAdjacentlyTagged::from(message))
+        }
+        Representation::Untagged => serde_json::to_string(&MessageUntagged::from(message)),
+    }
+}
+
+/// The members of a JSON object in the order they were written, with their
+/// values left unparsed.
+struct Entries<'a>(Vec<(String, &'a RawValue)>);
+
+impl<'de: 'a, 'a> Deserialize<'de> for Entries<'a> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct EntriesVisitor;
+
+        impl<'de> Visitor<'de> for EntriesVisitor {
+            type Value = Entries<'de>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("an object")
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+            where
+                A: MapAccess<'de>,
+            {
+                let mut entries = Vec::with_capacity(map.size_hint().unwrap_or(0));
+                while let Some(entry) = map.next_entry()? {
+                    entries.push(entry);
+                }
+                Ok(Entries(entries))
+            }
+        }
+
+        deserializer.deserialize_map(EntriesVisitor)
+    }
+}
+
+impl<'a> Serialize for Entries<'a> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_map(self.0.iter().map(|(key, value)| (key, value)))
+    }
+}
+
+impl<'a> Entries<'a> {
+    /// Writes the members back as an object. The values are written as
+    /// they were read, only the space between the members is lost.
+    fn to_raw(&self) -> Box<RawValue> {
+        serde_json::value::to_raw_value(self).expect("an object of JSON values is JSON")
+    }
+}
+
+/// Adds `"type": tag` in front of the members of the object in `content`,
+/// replacing any `"type"` it had. Content that is not an object is kept
+/// under `"content"`.
+fn insert_tag(content: &RawValue, tag: &str) -> Box<RawValue> {
+    let tag = serde_json::value::to_raw_value(tag).expect("a string is JSON");
+    let mut members = match serde_json::from_str::<Entries>(content.get()) {
+        Ok(Entries(members)) => members,
+        Err(_) => vec![("content".to_owned(), content)],
+    };
+    members.retain(|(key, _)| key != "type");
+    let mut entries = Entries(vec![("type".to_owned(), &*tag)]);
+    entries.0.extend(members);
+    entries.to_raw()
+}
+
+/// Removes the `"type"` member from the object in `raw`, keeping the other
+/// members in their order.
+fn remove_tag(raw: Box<RawValue>) -> Box<RawValue> {
+    match serde_json::from_str::<Entries>(raw.get()) {
+        Ok(Entries(mut members)) => {
+            members.retain(|(key, _)| key != "type");
+            Entries(members).to_raw()
+        }
+        Err(_) => raw,
+    }
+}
+
+impl<R> From<MessageInternallyTagged<R>> for MessageDefault<R> {
+    fn from(message: MessageInternallyTagged<R>) -> Self {
+        match message {
+            MessageInternallyTagged::Request { id, method, params } => {
+                MessageDefault::Request { id, method, params }
+            }
+            MessageInternallyTagged::Response { id, result } => {
+                MessageDefault::Response { id, result }
+            }
+            MessageInternallyTagged::Unknown { tag, raw } => MessageDefault::Unknown {
+                tag,
+                raw: remove_tag(raw),
+            },
+        }
+    }
+}
+
+impl<R> From<MessageDefault<R>> for MessageInternallyTagged<R> {
+    fn from(message: MessageDefault<R>) -> Self {
+        match message {
+            MessageDefault::Request { id, method, params } => {
+                MessageInternallyTagged::Request { id, method, params }
+            }
+            MessageDefault::Response { id, result } => {
+                MessageInternallyTagged::Response { id, result }
+            }
+            MessageDefault::Unknown { tag, raw } => MessageInternallyTagged::Unknown {
+                raw: insert_tag(&raw, &tag),
+                tag,
+            },
+        }
+    }
+}
+
+impl<R> From<MessageAdjacentlyTagged<R>> for MessageDefault<R> {
+    fn from(message: MessageAdjacentlyTagged<R>) -> Self {
+        match message {
+            MessageAdjacentlyTagged::Request { id, method, params } => {
+                MessageDefault::Request { id, method, params }
+            }
+            MessageAdjacentlyTagged::Response { id, result } => {
+                MessageDefault::Response { id, result }
+            }
+            MessageAdjacentlyTagged::Unknown { tag, raw } => MessageDefault::Unknown {
+                tag,
+                raw: raw.unwrap_or_else(|| RawValue::NULL.to_owned()),
+            },
+        }
+    }
+}
+
+impl<R> From<MessageDefault<R>> for MessageAdjacentlyTagged<R> {
+    fn from(message: MessageDefault<R>) -> Self {
+        match message {
+            MessageDefault::Request { id, method, params } => {
+                MessageAdjacentlyTagged::Request { id, method, params }
+            }
+            MessageDefault::Response { id, result } => {
+                MessageAdjacentlyTagged::Response { id, result }
+            }
+            MessageDefault::Unknown { tag, raw } => MessageAdjacentlyTagged::Unknown {
+                tag,
+                raw: Some(raw),
+            },
+        }
+    }
+}
+
+impl<R> From<MessageUntagged<R>> for MessageDefault<R> {
+    fn from(message: MessageUntagged<R>) -> Self {
+        match message {
+            MessageUntagged::Request {
+                id, method, params, ..
+            } => MessageDefault::Request { id, method, params },
+            MessageUntagged::Response { id, result } => MessageDefault::Response { id, result },
+            MessageUntagged::Unknown { raw } => MessageDefault::Unknown {
+                tag: UNTAGGED.to_owned(),
+                raw,
+            },
+        }
+    }
+}
+
+impl<R> From<MessageDefault<R>> for MessageUntagged<R> {
+    fn from(message: MessageDefault<R>) -> Self {
+        match message {
+            MessageDefault::Request { id, method, params } => MessageUntagged::Request {
+                id,
+                method,
+                tuple: (0, 0),
+                params,
+            },
+            MessageDefault::Response { id, result } => MessageUntagged::Response { id, result },
+            MessageDefault::Unknown { raw, .. } => MessageUntagged::Unknown { raw },
+        }
+    }
+}
+
+/// Converts between two enums other than `MessageDefault` by way of it.
+macro_rules! via_default {
+    ($($from:ident => $to:ident,)*) => {
+        $(
+            impl<R> From<$from<R>> for $to<R> {
+                fn from(message: $from<R>) -> Self {
+                    MessageDefault::from(message).into()
+                }
+            }
+        )*
+    };
+}
+
+via_default! {
+    MessageInternallyTagged => MessageAdjacentlyTagged,
+    MessageInternallyTagged => MessageUntagged,
+    MessageAdjacentlyTagged => MessageInternallyTagged,
+    MessageAdjacentlyTagged => MessageUntagged,
+    MessageUntagged => MessageInternallyTagged,
+    MessageUntagged => MessageAdjacentlyTagged,
+}
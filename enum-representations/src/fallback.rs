@@ -0,0 +1,165 @@
+//! # Unknown variants
+//!
+//! A derived `Deserialize` rejects any tag it does not know, so a message
+//! kind added by a newer peer makes the whole payload fail. To forward such
+//! a message instead, the `Message*` enums have an `Unknown` variant that
+//! keeps its content as a `RawValue`, the exact JSON text it was read from.
+//! Serializing a `RawValue` with `serde_json` writes that text back
+//! unchanged: the order of the members, the whitespace inside and the
+//! spelling of numbers such as `1.0` or `1e3` are all kept.
+//!
+//! The message is read as a `Payload` first, and split into members to find
+//! the tag. A known variant is then deserialized from a `Value`, and its
+//! errors get the position that the outer `Deserializer` reports.
+//!
+//! Only `serde_json` can hand out the text of a message, and only when it
+//! reads the message itself. Other formats, and the buffered content of an
+//! untagged or flattened parent, give a `Value` instead. Known variants read
+//! the same either way, but the `raw` of an `Unknown` is then that `Value`
+//! written out again, so the order of the members, the whitespace and the
+//! spelling of numbers are no longer those of the input.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde::de::{self, Deserialize, Deserializer, MapAccess, Visitor};
+use serde_json::value::RawValue;
+use serde_json::{Map, Value};
+
+/// The variants that the tagged `Message*` enums know.
+pub const VARIANTS: &[&str] = &["Request", "Response"];
+
+/// The newtype struct name under which `serde_json` hands out the text of a
+/// value, as a map with this name as its only key. Every other
+/// `Deserializer` treats it as an ordinary newtype struct.
+const RAW_VALUE_TOKEN: &str = "$serde_json::private::RawValue";
+
+/// A message as it was read: its JSON text if there is any, and a `Value`
+/// otherwise.
+pub enum Payload {
+    Raw(Box<RawValue>),
+    Value(Value),
+}
+
+/// The members of a JSON object, with their values left unparsed if there
+/// is text.
+pub type Members = BTreeMap<String, Payload>;
+
+impl<'de> Deserialize<'de> for Payload {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_newtype_struct(RAW_VALUE_TOKEN, PayloadVisitor)
+    }
+}
+
+struct PayloadVisitor;
+
+impl<'de> Visitor<'de> for PayloadVisitor {
+    type Value = Payload;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a message")
+    }
+
+    fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Payload, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Value::deserialize(deserializer).map(Payload::Value)
+    }
+
+    /// Either the text from `serde_json`, or an object from a format that
+    /// reads newtype structs as whatever the input holds.
+    fn visit_map<A>(self, mut map: A) -> Result<Payload, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let first = match map.next_key::<String>()? {
+            Some(ref key) if key == RAW_VALUE_TOKEN => {
+                let text: String = map.next_value()?;
+                return RawValue::from_string(text)
+                    .map(Payload::Raw)
+                    .map_err(de::Error::custom);
+            }
+            first => first,
+        };
+        let mut object = Map::new();
+        if let Some(key) = first {
+            object.insert(key, map.next_value()?);
+            while let Some((key, value)) = map.next_entry()? {
+                object.insert(key, value);
+            }
+        }
+        Ok(Payload::Value(Value::Object(object)))
+    }
+}
+
+impl fmt::Display for Payload {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Payload::Raw(raw) => fmt::Display::fmt(raw, formatter),
+            Payload::Value(value) => fmt::Display::fmt(value, formatter),
+        }
+    }
+}
+
+impl Payload {
+    /// Splits an object into its members, without parsing the values of
+    /// members that are still text.
+    pub fn members<E: de::Error>(&self) -> Result<Members, E> {
+        let invalid = || E::custom(format!("invalid type: {}, expected an object", self));
+        match self {
+            Payload::Raw(raw) => serde_json::from_str::<BTreeMap<String, &RawValue>>(raw.get())
+                .map(|members| {
+                    members
+                        .into_iter()
+                        .map(|(key, raw)| (key, Payload::Raw(raw.to_owned())))
+                        .collect()
+                })
+                .map_err(|_| invalid()),
+            Payload::Value(Value::Object(object)) => Ok(object
+                .iter()
+                .map(|(key, value)| (key.clone(), Payload::Value(value.clone())))
+                .collect()),
+            Payload::Value(_) => Err(invalid()),
+        }
+    }
+
+    /// Parses the text into a `Value`, to deserialize a known variant from.
+    pub fn into_value<E: de::Error>(self) -> Result<Value, E> {
+        match self {
+            Payload::Raw(raw) => serde_json::from_str(raw.get()).map_err(E::custom),
+            Payload::Value(value) => Ok(value),
+        }
+    }
+
+    /// The text to keep in an `Unknown`, written from the `Value` if there
+    /// is no text.
+    pub fn into_raw<E: de::Error>(self) -> Result<Box<RawValue>, E> {
+        match self {
+            Payload::Raw(raw) => Ok(raw),
+            Payload::Value(value) => serde_json::value::to_raw_value(&value).map_err(E::custom),
+        }
+    }
+}
+
+/// Reads the string tag stored under `key`, if there is one.
+pub fn tag<E: de::Error>(members: &Members, key: &str) -> Result<Option<String>, E> {
+    members
+        .get(key)
+        .map(|member| {
+            let tag = match member {
+                Payload::Raw(raw) => serde_json::from_str(raw.get()),
+                Payload::Value(value) => String::deserialize(value),
+            };
+            tag.map_err(|_| E::custom(format!("invalid type: {}, expected a string tag", member)))
+        })
+        .transpose()
+}
+
+/// Whether `tag` names a variant that is not in `VARIANTS`.
+pub fn is_unknown(tag: &str) -> bool {
+    !VARIANTS.contains(&tag)
+}
@@ -0,0 +1,348 @@
+//! # JSON-RPC 2.0
+//!
+//! Source: [https://www.jsonrpc.org/specification](https://www.jsonrpc.org/specification)
+//!
+//! None of the enum representations in the crate root fits JSON-RPC on its
+//! own. There is no tag at all: a request is told apart from a notification
+//! by whether it has an `id`, and a success from an error by whether it has a
+//! `result` or an `error`. `#[serde(untagged)]` would try each variant in
+//! turn and could mistake a malformed request for a response, so `Message`
+//! and `Response` look at which members are present first and then
+//! deserialize the one variant those members call for.
+
+use serde::de::{self, Deserializer, Unexpected};
+use serde::ser::SerializeSeq;
+use serde::{Deserialize, Serialize, Serializer};
+use serde_json::{Map, Value};
+
+/// The `"jsonrpc"` member, which must be exactly `"2.0"`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Version;
+
+impl Serialize for Version {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str("2.0")
+    }
+}
+
+impl<'de> Deserialize<'de> for Version {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let version = String::deserialize(deserializer)?;
+        if version == "2.0" {
+            Ok(Version)
+        } else {
+            Err(de::Error::invalid_value(
+                Unexpected::Str(&version),
+                &"\"2.0\"",
+            ))
+        }
+    }
+}
+
+/// The identifier a client chose for a request. A response carries the same
+/// `id`, or `null` if the `id` of the request could not be determined.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(untagged)]
+pub enum Id {
+    Number(i64),
+    String(String),
+    Null,
+}
+
+/// The parameters of a call, given either by position or by name: an array
+/// is `ByPosition` and an object is `ByName`.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum Params {
+    ByPosition(Vec<Value>),
+    ByName(Map<String, Value>),
+}
+
+/// A call that expects a response.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Request {
+    pub jsonrpc: Version,
+    pub method: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub params: Option<Params>,
+    pub id: Id,
+}
+
+/// A call without an `id`, to which the server must not respond.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Notification {
+    pub jsonrpc: Version,
+    pub method: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub params: Option<Params>,
+}
+
+/// The response to a `Request` that succeeded.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Success {
+    pub jsonrpc: Version,
+    pub result: Value,
+    pub id: Id,
+}
+
+/// The response to a `Request` that failed.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Failure {
+    pub jsonrpc: Version,
+    pub error: ErrorObject,
+    pub id: Id,
+}
+
+/// The `error` member of a `Failure`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ErrorObject {
+    pub code: ErrorCode,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+}
+
+/// The error codes reserved by the specification. Any other code is kept as
+/// `Other`, so every integer round trips.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(from = "i64", into = "i64")]
+pub enum ErrorCode {
+    /// Invalid JSON was received by the server.
+    ParseError,
+    /// The JSON sent is not a valid request object.
+    InvalidRequest,
+    /// The method does not exist or is not available.
+    MethodNotFound,
+    /// Invalid method parameters.
+    InvalidParams,
+    /// Internal JSON-RPC error.
+    InternalError,
+    /// Reserved for implementation-defined server errors, from -32099 to
+    /// -32000.
+    ServerError(i64),
+    /// A code defined by the application.
+    Other(i64),
+}
+
+impl From<i64> for ErrorCode {
+    fn from(code: i64) -> ErrorCode {
+        match code {
+            -32700 => ErrorCode::ParseError,
+            -32600 => ErrorCode::InvalidRequest,
+            -32601 => ErrorCode::MethodNotFound,
+            -32602 => ErrorCode::InvalidParams,
+            -32603 => ErrorCode::InternalError,
+            -32099..=-32000 => ErrorCode::ServerError(code),
+            _ => ErrorCode::Other(code),
+        }
+    }
+}
+
+impl From<ErrorCode> for i64 {
+    fn from(code: ErrorCode) -> i64 {
+        match code {
+            ErrorCode::ParseError => -32700,
+            ErrorCode::InvalidRequest => -32600,
+            ErrorCode::MethodNotFound => -32601,
+            ErrorCode::InvalidParams => -32602,
+            ErrorCode::InternalError => -32603,
+            ErrorCode::ServerError(code) | ErrorCode::Other(code) => code,
+        }
+    }
+}
+
+/// A `Success` or a `Failure`, told apart by whether the object has a
+/// `result` or an `error`.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum Response {
+    Success(Success),
+    Failure(Failure),
+}
+
+/// Any single JSON-RPC message.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum Message {
+    Request(Request),
+    Notification(Notification),
+    Response(Response),
+}
+
+/// What a client or server receives: a single message, or a non-empty batch
+/// of them.
+///
+/// Every element of a batch is answered on its own, so an element that is
+/// not a valid message is kept as an `Invalid` instead of rejecting the
+/// whole batch.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Payload {
+    Single(Message),
+    Batch(Vec<Result<Message, Invalid>>),
+}
+
+/// A batch element that is not a valid message, and why.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Invalid {
+    pub value: Value,
+    pub error: String,
+}
+
+/// Writes a batch element back as it was read, valid or not.
+struct Element<'a>(&'a Result<Message, Invalid>);
+
+impl Serialize for Element<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self.0 {
+            Ok(message) => message.serialize(serializer),
+            Err(invalid) => invalid.value.serialize(serializer),
+        }
+    }
+}
+
+impl Serialize for Payload {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            Payload::Single(message) => message.serialize(serializer),
+            Payload::Batch(elements) => {
+                let mut seq = serializer.serialize_seq(Some(elements.len()))?;
+                for element in elements {
+                    seq.serialize_element(&Element(element))?;
+                }
+                seq.end()
+            }
+        }
+    }
+}
+
+/// Describes `value` for an `invalid_type` error.
+fn unexpected(value: &Value) -> Unexpected<'_> {
+    match value {
+        Value::Null => Unexpected::Unit,
+        Value::Bool(b) => Unexpected::Bool(*b),
+        Value::Number(_) => Unexpected::Other("number"),
+        Value::String(s) => Unexpected::Str(s),
+        Value::Array(_) => Unexpected::Seq,
+        Value::Object(_) => Unexpected::Map,
+    }
+}
+
+/// Deserializes the variant that was chosen for `value`, turning the
+/// `serde_json` error into an error of the outer `Deserializer`.
+fn from_value<T, E>(value: Value) -> Result<T, E>
+where
+    T: de::DeserializeOwned,
+    E: de::Error,
+{
+    serde_json::from_value(value).map_err(E::custom)
+}
+
+/// Reads a response object, which must have exactly one of `result` and
+/// `error`.
+fn response_from_value<E: de::Error>(value: Value) -> Result<Response, E> {
+    let object = match value.as_object() {
+        Some(object) => object,
+        None => return Err(E::invalid_type(unexpected(&value), &"a response object")),
+    };
+    match (object.contains_key("result"), object.contains_key("error")) {
+        (true, false) => from_value(value).map(Response::Success),
+        (false, true) => from_value(value).map(Response::Failure),
+        (true, true) => Err(E::custom(
+            "a response must not have both `result` and `error`",
+        )),
+        (false, false) => Err(E::custom("a response must have either `result` or `error`")),
+    }
+}
+
+/// Reads any message object. `method` makes it a call, which is a `Request`
+/// if it has an `id` and a `Notification` otherwise. Anything else has to be
+/// a response.
+fn message_from_value<E: de::Error>(value: Value) -> Result<Message, E> {
+    let object = match value.as_object() {
+        Some(object) => object,
+        None => return Err(E::invalid_type(unexpected(&value), &"a message object")),
+    };
+    if object.contains_key("method") {
+        if object.contains_key("id") {
+            from_value(value).map(Message::Request)
+        } else {
+            from_value(value).map(Message::Notification)
+        }
+    } else {
+        response_from_value(value).map(Message::Response)
+    }
+}
+
+impl<'de> Deserialize<'de> for Params {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match Value::deserialize(deserializer)? {
+            Value::Array(values) => Ok(Params::ByPosition(values)),
+            Value::Object(members) => Ok(Params::ByName(members)),
+            value => Err(de::Error::invalid_type(
+                unexpected(&value),
+                &"params to be an array or an object",
+            )),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Response {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        response_from_value(Value::deserialize(deserializer)?)
+    }
+}
+
+impl<'de> Deserialize<'de> for Message {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        message_from_value(Value::deserialize(deserializer)?)
+    }
+}
+
+impl<'de> Deserialize<'de> for Payload {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match Value::deserialize(deserializer)? {
+            Value::Array(values) => {
+                if values.is_empty() {
+                    return Err(de::Error::invalid_length(0, &"a non-empty batch"));
+                }
+                let elements = values
+                    .into_iter()
+                    .map(|value| {
+                        message_from_value(value.clone()).map_err(|err: serde_json::Error| {
+                            Invalid {
+                                value,
+                                error: err.to_string(),
+                            }
+                        })
+                    })
+                    .collect();
+                Ok(Payload::Batch(elements))
+            }
+            value => message_from_value(value).map(Payload::Single),
+        }
+    }
+}
@@ -0,0 +1,422 @@
+//! # Enum representations
+//!
+//! Source: [https://serde.rs/enum-representations.html](https://serde.rs/enum-representations.html)
+//!
+//! The message types live in the library, so that both the tutorial in
+//! `main.rs` and the `detect-representation` binary can use them. The
+//! helpers behind them, `by_key` for untagged enums and `tag` for
+//! non-string tags, are public so that other enums can use them too.
+
+pub mod borrowed;
+pub mod by_key;
+pub mod convert;
+mod fallback;
+pub mod jsonrpc;
+pub mod registry;
+pub mod results;
+pub mod tag;
+pub mod transport;
+
+use serde::de::{self, DeserializeOwned};
+use serde::ser::SerializeMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::value::RawValue;
+
+use fallback::Payload;
+use tag::Tag;
+
+/// Default representation for an enum in Serde is the externally tagged enum
+/// representation. The variant is explicit. Not ideal for JSON.
+#[derive(Debug)]
+pub enum MessageDefault<R = serde_json::Value> {
+    Request {
+        id: String,
+        method: String,
+        params: Params,
+    },
+    Response {
+        id: String,
+        result: R,
+    },
+    /// Any other variant, written back as `{tag: raw}`.
+    Unknown {
+        tag: String,
+        raw: Box<RawValue>,
+    },
+}
+
+/// The derived impls of `MessageDefault`, which handle every variant but
+/// `Unknown`.
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "MessageDefault")]
+enum MessageDefaultDef<R> {
+    Request {
+        id: String,
+        method: String,
+        params: Params,
+    },
+    Response {
+        id: String,
+        result: R,
+    },
+    #[serde(skip)]
+    Unknown {
+        tag: String,
+        raw: Box<RawValue>,
+    },
+}
+
+impl<R: Serialize> Serialize for MessageDefault<R> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            MessageDefault::Unknown { tag, raw } => {
+                let mut map = serializer.serialize_map(Some(1))?;
+                map.serialize_entry(tag, raw)?;
+                map.end()
+            }
+            known => MessageDefaultDef::serialize(known, serializer),
+        }
+    }
+}
+
+impl<'de, R: DeserializeOwned> Deserialize<'de> for MessageDefault<R> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let payload = Payload::deserialize(deserializer)?;
+        let members = payload.members()?;
+        if let (1, Some((tag, content))) = (members.len(), members.into_iter().next()) {
+            if fallback::is_unknown(&tag) {
+                return Ok(MessageDefault::Unknown {
+                    tag,
+                    raw: content.into_raw()?,
+                });
+            }
+        }
+        MessageDefaultDef::deserialize(payload.into_value()?).map_err(de::Error::custom)
+    }
+}
+
+/// The tag identifying the variant is not inside of the content, next to other
+/// fields of the variant. Works for enums that contains struct variants,
+/// newtype variants containing structs or map, and unit vairants, but does not
+/// work for tuple variants.
+#[derive(Debug)]
+pub enum MessageInternallyTagged<R = serde_json::Value> {
+    Request {
+        id: String,
+        method: String,
+        params: Params,
+    },
+    Response {
+        id: String,
+        result: R,
+    },
+    /// Any other variant. `raw` is the whole object, tag included.
+    Unknown {
+        tag: String,
+        raw: Box<RawValue>,
+    },
+}
+
+/// The derived impls of `MessageInternallyTagged`, which handle every
+/// variant but `Unknown`.
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "MessageInternallyTagged", tag = "type")]
+enum MessageInternallyTaggedDef<R> {
+    Request {
+        id: String,
+        method: String,
+        params: Params,
+    },
+    Response {
+        id: String,
+        result: R,
+    },
+    #[serde(skip)]
+    Unknown {
+        tag: String,
+        raw: Box<RawValue>,
+    },
+}
+
+impl<R: Serialize> Serialize for MessageInternallyTagged<R> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            MessageInternallyTagged::Unknown { raw, .. } => raw.serialize(serializer),
+            known => MessageInternallyTaggedDef::serialize(known, serializer),
+        }
+    }
+}
+
+impl<'de, R: DeserializeOwned> Deserialize<'de> for MessageInternallyTagged<R> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let payload = Payload::deserialize(deserializer)?;
+        match fallback::tag(&payload.members()?, "type")? {
+            Some(tag) if fallback::is_unknown(&tag) => Ok(MessageInternallyTagged::Unknown {
+                tag,
+                raw: payload.into_raw()?,
+            }),
+            _ => MessageInternallyTaggedDef::deserialize(payload.into_value()?)
+                .map_err(de::Error::custom),
+        }
+    }
+}
+
+/// Internally tagged like `MessageInternallyTagged`, but with the numeric
+/// message types of a binary protocol as tags. The variant names are still
+/// accepted as aliases, and `true`/`false` as those of older producers.
+#[derive(Debug)]
+pub enum MessageNumericTagged<R = serde_json::Value> {
+    Request {
+        id: String,
+        method: String,
+        params: Params,
+    },
+    Response {
+        id: String,
+        result: R,
+    },
+}
+
+/// An externally tagged copy of `MessageNumericTagged`, which `tag` converts
+/// from and to the internally tagged form.
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "MessageNumericTagged")]
+enum MessageNumericTaggedDef<R> {
+    Request {
+        id: String,
+        method: String,
+        params: Params,
+    },
+    Response {
+        id: String,
+        result: R,
+    },
+}
+
+impl<R: Serialize + DeserializeOwned> tag::Tagged for MessageNumericTagged<R> {
+    const TAG: &'static str = "type";
+
+    const VARIANTS: &'static [tag::Variant] = &[
+        tag::Variant {
+            name: "Request",
+            tags: &[Tag::Int(1), Tag::Str("Request"), Tag::Bool(false)],
+        },
+        tag::Variant {
+            name: "Response",
+            tags: &[Tag::Int(2), Tag::Str("Response"), Tag::Bool(true)],
+        },
+    ];
+
+    fn to_externally_tagged(&self) -> Result<serde_json::Value, serde_json::Error> {
+        #[derive(Serialize)]
+        #[serde(bound = "R: Serialize")]
+        struct ExternallyTagged<'a, R>(
+            #[serde(with = "MessageNumericTaggedDef")] &'a MessageNumericTagged<R>,
+        );
+
+        serde_json::to_value(ExternallyTagged(self))
+    }
+
+    fn from_externally_tagged(value: serde_json::Value) -> Result<Self, serde_json::Error> {
+        MessageNumericTaggedDef::deserialize(value)
+    }
+}
+
+impl<R: Serialize + DeserializeOwned> Serialize for MessageNumericTagged<R> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        tag::serialize(self, serializer)
+    }
+}
+
+impl<'de, R: Serialize + DeserializeOwned> Deserialize<'de> for MessageNumericTagged<R> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        tag::deserialize(deserializer)
+    }
+}
+
+#[derive(Debug)]
+pub enum MessageAdjacentlyTagged<R = serde_json::Value> {
+    Request {
+        id: String,
+        method: String,
+        params: Params,
+    },
+    Response {
+        id: String,
+        result: R,
+    },
+    /// Any other variant. `raw` is the content under `"c"`, if there was
+    /// any.
+    Unknown {
+        tag: String,
+        raw: Option<Box<RawValue>>,
+    },
+}
+
+/// The derived impls of `MessageAdjacentlyTagged`, which handle every
+/// variant but `Unknown`.
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "MessageAdjacentlyTagged", tag = "t", content = "c")]
+enum MessageAdjacentlyTaggedDef<R> {
+    Request {
+        id: String,
+        method: String,
+        params: Params,
+    },
+    Response {
+        id: String,
+        result: R,
+    },
+    #[serde(skip)]
+    Unknown {
+        tag: String,
+        raw: Option<Box<RawValue>>,
+    },
+}
+
+impl<R: Serialize> Serialize for MessageAdjacentlyTagged<R> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            MessageAdjacentlyTagged::Unknown { tag, raw } => {
+                let mut map = serializer.serialize_map(Some(1 + raw.is_some() as usize))?;
+                map.serialize_entry("t", tag)?;
+                if let Some(raw) = raw {
+                    map.serialize_entry("c", raw)?;
+                }
+                map.end()
+            }
+            known => MessageAdjacentlyTaggedDef::serialize(known, serializer),
+        }
+    }
+}
+
+impl<'de, R: DeserializeOwned> Deserialize<'de> for MessageAdjacentlyTagged<R> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let payload = Payload::deserialize(deserializer)?;
+        let mut members = payload.members()?;
+        match fallback::tag(&members, "t")? {
+            Some(tag) if fallback::is_unknown(&tag) => Ok(MessageAdjacentlyTagged::Unknown {
+                tag,
+                raw: members.remove("c").map(Payload::into_raw).transpose()?,
+            }),
+            _ => MessageAdjacentlyTaggedDef::deserialize(payload.into_value()?)
+                .map_err(de::Error::custom),
+        }
+    }
+}
+
+/// There is no tag at all. Serializing writes only the content of the
+/// variant. Deriving `Deserialize` with `#[serde(untagged)]` would try each
+/// variant in turn, so instead the variant is picked by `by_key`: a
+/// `method` means `Request` and a `result` means `Response`. An object with
+/// neither is only kept as `Unknown` if it shares no field with the known
+/// variants, so that a known message with a misspelled key is still an
+/// error.
+#[derive(Serialize, Debug)]
+#[serde(untagged)]
+pub enum MessageUntagged<R = serde_json::Value> {
+    Request {
+        id: String,
+        method: String,
+        tuple: (u32, u32),
+        params: Params,
+    },
+    Response {
+        id: String,
+        result: R,
+    },
+    /// Any other object. Without a tag there is only `raw`, the whole
+    /// object.
+    #[serde(serialize_with = "Serialize::serialize")]
+    Unknown {
+        raw: Box<RawValue>,
+    },
+}
+
+/// An externally tagged copy of `MessageUntagged`, which `by_key` uses to
+/// deserialize the variant it picked.
+#[derive(Deserialize)]
+#[serde(remote = "MessageUntagged")]
+enum MessageUntaggedDef<R> {
+    Request {
+        id: String,
+        method: String,
+        tuple: (u32, u32),
+        params: Params,
+    },
+    Response {
+        id: String,
+        result: R,
+    },
+    #[serde(skip)]
+    Unknown {
+        raw: Box<RawValue>,
+    },
+}
+
+impl<'de, R: DeserializeOwned> Deserialize<'de> for MessageUntagged<R> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        const KEYS: &[(&str, &str)] = &[("method", "Request"), ("result", "Response")];
+        const FIELDS: &[&str] = &["id", "method", "tuple", "params", "result"];
+
+        let payload = Payload::deserialize(deserializer)?;
+        let members = payload.members()?;
+        if !members.is_empty() && !members.keys().any(|key| FIELDS.contains(&key.as_str())) {
+            return Ok(MessageUntagged::Unknown {
+                raw: payload.into_raw()?,
+            });
+        }
+        by_key::deserialize(payload.into_value()?, KEYS, MessageUntaggedDef::deserialize)
+            .map_err(de::Error::custom)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Params {
+    pub field_1: u32,
+    pub field_2: bool,
+    pub field_3: Vec<i32>,
+}
+
+impl Params {
+    pub fn new() -> Params {
+        Params {
+            field_1: 30,
+            field_2: false,
+            field_3: vec![-4, -4, 0, 3],
+        }
+    }
+}
+
+impl Default for Params {
+    fn default() -> Params {
+        Params::new()
+    }
+}
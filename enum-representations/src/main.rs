@@ -2,91 +2,70 @@
 //!
 //! Source: [https://serde.rs/enum-representations.html](https://serde.rs/enum-representations.html)
 
+use std::borrow::Cow;
+use std::collections::HashMap;
+
 use serde::{Deserialize, Serialize};
 
-/// Default representation for an enum in Serde is the externally tagged enum
-/// representation. The variant is explicit. Not ideal for JSON.
+use enum_representations::borrowed;
+use enum_representations::convert::{self, Representation};
+use enum_representations::jsonrpc::{ErrorCode, ErrorObject, Id};
+use enum_representations::registry::{Client, Method, Registry};
+use enum_representations::results::MethodResult;
+use enum_representations::transport::Memory;
+use enum_representations::{
+    jsonrpc, MessageAdjacentlyTagged, MessageDefault, MessageInternallyTagged,
+    MessageNumericTagged, MessageUntagged, Params,
+};
+
+/// The result of a `GET`.
 #[derive(Serialize, Deserialize, Debug)]
-enum MessageDefault {
-    Request {
-        id: String,
-        method: String,
-        params: Params,
-    },
-    Response {
-        id: String,
-        result: Value,
-    },
+struct Resource {
+    field_1: bool,
+    field_2: String,
 }
 
-/// The tag identifying the variant is not inside of the content, next to other
-/// fields of the variant. Works for enums that contains struct variants,
-/// newtype variants containing structs or map, and unit vairants, but does not
-/// work for tuple variants.
-#[derive(Serialize, Deserialize, Debug)]
-#[serde(tag = "type")]
-enum MessageInternallyTagged {
-    Request {
-        id: String,
-        method: String,
-        params: Params,
-    },
-    Response {
-        id: String,
-        result: Value,
-    },
+/// The results of the methods this client calls.
+#[derive(Debug)]
+enum CallResult {
+    Get(Resource),
+    Sum(i64),
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-#[serde(tag = "t", content = "c")]
-enum MessageAdjacentlyTagged {
-    Request {
-        id: String,
-        method: String,
-        params: Params,
-    },
-    Response {
-        id: String,
-        result: Value,
-    },
+impl MethodResult for CallResult {
+    fn decode(method: &str, result: serde_json::Value) -> Result<Self, serde_json::Error> {
+        match method {
+            "GET" => serde_json::from_value(result).map(CallResult::Get),
+            "sum" => serde_json::from_value(result).map(CallResult::Sum),
+            _ => Err(serde::de::Error::custom(format!(
+                "no result type for method `{}`",
+                method
+            ))),
+        }
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-#[serde(untagged)]
-enum MessageUntagged {
-    Request {
-        id: String,
-        method: String,
-        tuple: (u32, u32),
-        params: Params,
-    },
-    Response {
-        id: String,
-        result: Value,
-    },
+/// `subtract`, from the JSON-RPC specification, with params by position.
+struct Subtract;
+
+impl Method for Subtract {
+    const NAME: &'static str = "subtract";
+    type Params = (i64, i64);
+    type Result = i64;
 }
 
 #[derive(Serialize, Deserialize, Debug)]
-struct Params {
-    field_1: u32,
-    field_2: bool,
-    field_3: Vec<i32>,
+struct GetParams {
+    path: String,
 }
 
-impl Params {
-    fn new() -> Params {
-        Params {
-            field_1: 30,
-            field_2: false,
-            field_3: vec![-4, -4, 0, 3],
-        }
-    }
-}
+/// `GET`, with params by name.
+struct Get;
 
-#[derive(Serialize, Deserialize, Debug)]
-struct Value {
-    field_1: bool,
-    field_2: String,
+impl Method for Get {
+    const NAME: &'static str = "GET";
+    type Params = GetParams;
+    type Result = Resource;
 }
 
 fn print_serialized<T: Serialize>(serialize: &T) {
@@ -94,8 +73,48 @@ fn print_serialized<T: Serialize>(serialize: &T) {
     println!("Serialized request = {}\n", serialized);
 }
 
+/// The kind of each message in `payload`, for checking the examples from the
+/// specification.
+fn kinds(payload: &jsonrpc::Payload) -> Vec<&'static str> {
+    fn kind(message: &jsonrpc::Message) -> &'static str {
+        match message {
+            jsonrpc::Message::Request(_) => "request",
+            jsonrpc::Message::Notification(_) => "notification",
+            jsonrpc::Message::Response(jsonrpc::Response::Success(_)) => "success",
+            jsonrpc::Message::Response(jsonrpc::Response::Failure(_)) => "failure",
+        }
+    }
+
+    match payload {
+        jsonrpc::Payload::Single(message) => vec![kind(message)],
+        jsonrpc::Payload::Batch(elements) => elements
+            .iter()
+            .map(|element| element.as_ref().map_or("invalid", kind))
+            .collect(),
+    }
+}
+
+/// The error code of every response in `json`, a single response or a
+/// batch of them, or `None` for each success.
+fn codes(json: &str) -> Vec<Option<i64>> {
+    fn code(response: &jsonrpc::Response) -> Option<i64> {
+        match response {
+            jsonrpc::Response::Success(_) => None,
+            jsonrpc::Response::Failure(failure) => Some(i64::from(failure.error.code)),
+        }
+    }
+
+    match serde_json::from_str(json).unwrap() {
+        serde_json::Value::Array(responses) => responses
+            .into_iter()
+            .map(|response| code(&serde_json::from_value(response).unwrap()))
+            .collect(),
+        response => vec![code(&serde_json::from_value(response).unwrap())],
+    }
+}
+
 fn main() {
-    let request = MessageDefault::Request {
+    let request: MessageDefault = MessageDefault::Request {
         id: String::from("a37cacc3-71d5-40f0-a329-a051a3949ced"),
         method: String::from("GET"),
         params: Params::new(),
@@ -103,7 +122,7 @@ fn main() {
     println!("Default:");
     print_serialized(&request);
 
-    let request = MessageInternallyTagged::Request {
+    let request: MessageInternallyTagged = MessageInternallyTagged::Request {
         id: String::from("a37cacc3-71d5-40f0-a329-a051a3949ced"),
         method: String::from("GET"),
         params: Params::new(),
@@ -111,7 +130,43 @@ fn main() {
     println!("Internally Tagged:");
     print_serialized(&request);
 
-    let request = MessageAdjacentlyTagged::Request {
+    let request: MessageNumericTagged = MessageNumericTagged::Request {
+        id: String::from("a37cacc3-71d5-40f0-a329-a051a3949ced"),
+        method: String::from("GET"),
+        params: Params::new(),
+    };
+    println!("Internally Tagged, numeric tags:");
+    print_serialized(&request);
+
+    // Every alias reads as the same variant, and writing it back always uses
+    // the first tag of the variant.
+    let aliases = [
+        r#"{"type": 2, "id": "1", "result": {"field_1": true, "field_2": "ok"}}"#,
+        r#"{"id": "1", "type": "Response", "result": {"field_1": true, "field_2": "ok"}}"#,
+        r#"{"type": true, "id": "1", "result": {"field_1": true, "field_2": "ok"}}"#,
+    ];
+    for json in &aliases {
+        let message: MessageNumericTagged = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            serde_json::to_string(&message).unwrap(),
+            r#"{"type":2,"id":"1","result":{"field_1":true,"field_2":"ok"}}"#
+        );
+        println!("{} => {:?}", json, message);
+    }
+
+    let invalid = [
+        r#"{"type": 3, "id": "1"}"#,
+        r#"{"type": "request", "id": "1"}"#,
+        r#"{"id": "1", "method": "GET"}"#,
+        r#"{"type": 1, "id": "1", "method": "GET"}"#,
+    ];
+    for json in &invalid {
+        let err = serde_json::from_str::<MessageNumericTagged>(json).unwrap_err();
+        println!("{} => {}", json, err);
+    }
+    println!();
+
+    let request: MessageAdjacentlyTagged = MessageAdjacentlyTagged::Request {
         id: String::from("a37cacc3-71d5-40f0-a329-a051a3949ced"),
         method: String::from("GET"),
         params: Params::new(),
@@ -119,7 +174,7 @@ fn main() {
     println!("Adjacently Tagged:");
     print_serialized(&request);
 
-    let request = MessageUntagged::Request {
+    let request: MessageUntagged = MessageUntagged::Request {
         id: String::from("a37cacc3-71d5-40f0-a329-a051a3949ced"),
         method: String::from("GET"),
         tuple: (4, 3),
@@ -127,4 +182,489 @@ fn main() {
     };
     println!("Untagged:");
     print_serialized(&request);
+
+    // The examples from the JSON-RPC 2.0 specification, with the kind of
+    // message each one must read as. Invalid elements of a batch are kept,
+    // so that each can be answered on its own.
+    let examples: [(&str, &[&str]); 11] = [
+        (
+            r#"{"jsonrpc": "2.0", "method": "subtract", "params": [42, 23], "id": 1}"#,
+            &["request"],
+        ),
+        (
+            r#"{"jsonrpc": "2.0", "method": "subtract", "params": {"subtrahend": 23, "minuend": 42}, "id": 3}"#,
+            &["request"],
+        ),
+        (
+            r#"{"jsonrpc": "2.0", "method": "update", "params": [1,2,3,4,5]}"#,
+            &["notification"],
+        ),
+        (
+            r#"{"jsonrpc": "2.0", "method": "foobar"}"#,
+            &["notification"],
+        ),
+        (r#"{"jsonrpc": "2.0", "result": 19, "id": 1}"#, &["success"]),
+        (
+            r#"{"jsonrpc": "2.0", "error": {"code": -32601, "message": "Method not found"}, "id": "1"}"#,
+            &["failure"],
+        ),
+        (
+            r#"{"jsonrpc": "2.0", "error": {"code": -32700, "message": "Parse error"}, "id": null}"#,
+            &["failure"],
+        ),
+        (
+            r#"[
+                {"jsonrpc": "2.0", "method": "sum", "params": [1,2,4], "id": "1"},
+                {"jsonrpc": "2.0", "method": "notify_hello", "params": [7]}
+            ]"#,
+            &["request", "notification"],
+        ),
+        (r#"[1]"#, &["invalid"]),
+        (r#"[1,2,3]"#, &["invalid", "invalid", "invalid"]),
+        (
+            r#"[
+                {"jsonrpc": "2.0", "method": "sum", "params": [1,2,4], "id": "1"},
+                {"jsonrpc": "2.0", "method": "notify_hello", "params": [7]},
+                {"jsonrpc": "2.0", "method": "subtract", "params": [42,23], "id": "2"},
+                {"foo": "boo"},
+                {"jsonrpc": "2.0", "method": "foo.get", "params": {"name": "myself"}, "id": "5"},
+                {"jsonrpc": "2.0", "method": "get_data", "id": "9"}
+            ]"#,
+            &[
+                "request",
+                "notification",
+                "request",
+                "invalid",
+                "request",
+                "request",
+            ],
+        ),
+    ];
+    println!("JSON-RPC 2.0:");
+    for (example, expected) in &examples {
+        let payload: jsonrpc::Payload = serde_json::from_str(example).unwrap();
+        assert_eq!(kinds(&payload), *expected, "{}", example);
+        let serialized = serde_json::to_string(&payload).unwrap();
+        let reread: jsonrpc::Payload = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(reread, payload);
+        println!("{:?}", payload);
+    }
+
+    // A single message that is invalid, or an empty batch, cannot be read
+    // at all.
+    let invalid = [
+        r#"{"jsonrpc": "1.0", "method": "subtract", "id": 1}"#,
+        r#"{"jsonrpc": "2.0", "method": 1, "params": "bar"}"#,
+        r#"{"jsonrpc": "2.0", "result": 19, "error": {"code": 1, "message": ""}, "id": 1}"#,
+        r#"[]"#,
+    ];
+    for example in &invalid {
+        let err = serde_json::from_str::<jsonrpc::Payload>(example).unwrap_err();
+        println!("{} => {}", example, err);
+    }
+
+    // `params` is read by its shape, so a scalar says what was expected
+    // instead of "data did not match any variant".
+    let scalar = r#"{"jsonrpc": "2.0", "method": "subtract", "params": "bar", "id": 1}"#;
+    let err = serde_json::from_str::<jsonrpc::Payload>(scalar).unwrap_err();
+    println!("{} => {}", scalar, err);
+    assert!(
+        err.to_string()
+            .contains("expected params to be an array or an object"),
+        "{}",
+        err
+    );
+
+    // The field error of the variant the keys point to is reported, rather
+    // than "data did not match any variant".
+    println!("Untagged, dispatched by key:");
+    let malformed = [
+        r#"{"id": "1", "method": "GET", "tuple": [4, 3]}"#,
+        r#"{"id": 1, "result": {"field_1": true, "field_2": "ok"}}"#,
+    ];
+    for json in &malformed {
+        let err = serde_json::from_str::<MessageUntagged>(json).unwrap_err();
+        println!("{} => {}", json, err);
+    }
+    let json = r#"{"id": "1", "result": {"field_1": true, "field_2": "ok"}}"#;
+    println!(
+        "{:?}",
+        serde_json::from_str::<MessageUntagged>(json).unwrap()
+    );
+
+    // A response is read with a dynamic `result`, and decoded into the
+    // result type of the method of the request it answers once that request
+    // is known.
+    println!("Typed results:");
+    let sent: HashMap<_, _> = vec![("1", "GET"), ("2", "sum"), ("3", "sum"), ("4", "PUT")]
+        .into_iter()
+        .collect();
+    let responses = [
+        r#"{"type": "Response", "id": "1", "result": {"field_1": true, "field_2": "ok"}}"#,
+        r#"{"type": "Response", "id": "2", "result": 7}"#,
+        r#"{"type": "Response", "id": "3", "result": "7"}"#,
+        r#"{"type": "Response", "id": "4", "result": null}"#,
+    ];
+    for json in &responses {
+        let message: MessageInternallyTagged = serde_json::from_str(json).unwrap();
+        let method = match &message {
+            MessageInternallyTagged::Response { id, .. } => sent[id.as_str()],
+            _ => unreachable!(),
+        };
+        match message.decode_result::<CallResult>(method) {
+            Ok(MessageInternallyTagged::Response { result, .. }) => match result {
+                CallResult::Get(resource) => println!("{} => {:?}", method, resource),
+                CallResult::Sum(sum) => println!("{} => {}", method, sum),
+            },
+            Ok(_) => unreachable!(),
+            Err(err) => println!("{} => {}", method, err),
+        }
+    }
+
+    // A client and a server connected in memory. The server reads params
+    // as the type registered for the method, and the client reads each
+    // result as the result type of the method its request called.
+    println!("Method registry:");
+    let mut registry = Registry::new();
+    registry
+        .register::<Subtract, _>(|(minuend, subtrahend)| Ok(minuend - subtrahend))
+        .register::<Get, _>(|params| match params.path.as_str() {
+            "/" => Ok(Resource {
+                field_1: true,
+                field_2: String::from("index"),
+            }),
+            path => Err(ErrorObject {
+                code: ErrorCode::Other(404),
+                message: format!("no resource at {}", path),
+                data: None,
+            }),
+        });
+    let (client_end, mut server_end) = Memory::pair();
+    let mut client = Client::new(client_end);
+    let difference = client.call::<Subtract>(&(42, 23)).unwrap();
+    let index = client
+        .call::<Get>(&GetParams {
+            path: String::from("/"),
+        })
+        .unwrap();
+    let missing = client
+        .call::<Get>(&GetParams {
+            path: String::from("/missing"),
+        })
+        .unwrap();
+    registry.serve(&mut server_end);
+
+    // Responses are matched by id, whatever order they are read in.
+    println!("{:?}", client.result::<Get>(&index).unwrap());
+    assert_eq!(client.result::<Subtract>(&difference).unwrap(), 19);
+    println!("{}", client.result::<Get>(&missing).unwrap_err());
+    println!("{}", client.result::<Get>(&index).unwrap_err());
+    let difference = client.call::<Subtract>(&(1, 2)).unwrap();
+    println!("{}", client.result::<Get>(&difference).unwrap_err());
+
+    // Requests the registry cannot take are answered with the reserved
+    // errors, and notifications are not answered at all. Each expected
+    // answer is the error code of every response, or `None` for a success,
+    // following the examples of the specification.
+    let requests: [(&str, Option<&[Option<i64>]>); 11] = [
+        (
+            r#"{"jsonrpc": "2.0", "method": "subtract", "params": [42, 23], "id": 1}"#,
+            Some(&[None]),
+        ),
+        (
+            r#"{"jsonrpc": "2.0", "method": "subtract", "params": [42], "id": 1}"#,
+            Some(&[Some(-32602)]),
+        ),
+        (
+            r#"{"jsonrpc": "2.0", "method": "GET", "params": {"file": "/"}, "id": 2}"#,
+            Some(&[Some(-32602)]),
+        ),
+        (
+            r#"{"jsonrpc": "2.0", "method": "foobar", "id": "1"}"#,
+            Some(&[Some(-32601)]),
+        ),
+        (r#"{"jsonrpc": "2.0", "method": "foobar"}"#, None),
+        (
+            r#"{"jsonrpc": "2.0", "method": "foobar, "params": "bar", "baz]"#,
+            Some(&[Some(-32700)]),
+        ),
+        (
+            r#"{"jsonrpc": "2.0", "method": 1, "params": "bar"}"#,
+            Some(&[Some(-32600)]),
+        ),
+        (
+            r#"[
+                {"jsonrpc": "2.0", "method": "sum", "params": [1,2,4], "id": "1"},
+                {"jsonrpc": "2.0", "method"
+            ]"#,
+            Some(&[Some(-32700)]),
+        ),
+        (r#"[]"#, Some(&[Some(-32600)])),
+        (
+            r#"[1,2,3]"#,
+            Some(&[Some(-32600), Some(-32600), Some(-32600)]),
+        ),
+        (
+            r#"[
+                {"jsonrpc": "2.0", "method": "subtract", "params": [42,23], "id": "2"},
+                {"jsonrpc": "2.0", "method": "notify_hello", "params": [7]},
+                {"foo": "boo"},
+                {"jsonrpc": "2.0", "method": "get_data", "id": "9"}
+            ]"#,
+            Some(&[None, Some(-32600), Some(-32601)]),
+        ),
+    ];
+    for (request, expected) in &requests {
+        let response = registry.handle(request);
+        match &response {
+            Some(response) => println!("{} => {}", request, response),
+            None => println!("{} => no response", request),
+        }
+        assert_eq!(
+            response.map(|response| codes(&response)),
+            expected.map(<[_]>::to_vec)
+        );
+    }
+    let batch = r#"[{"jsonrpc": "2.0", "method": "notify_sum", "params": [1,2,4]}]"#;
+    assert_eq!(registry.handle(batch), None);
+
+    // The registry is also a `DeserializeSeed`, which fails unless the
+    // params have the type registered for the method.
+    let json = r#"{"jsonrpc": "2.0", "method": "subtract", "params": [5, 3], "id": 9}"#;
+    let mut de = serde_json::Deserializer::from_str(json);
+    let call = serde::de::DeserializeSeed::deserialize(&registry, &mut de).unwrap();
+    assert_eq!(call.params::<Subtract>(), Some(&(5, 3)));
+    assert_eq!(call.id, Some(Id::Number(9)));
+    println!("{:?}", call.run());
+    let json = r#"{"jsonrpc": "2.0", "method": "subtract", "params": {"a": 5}, "id": 9}"#;
+    let mut de = serde_json::Deserializer::from_str(json);
+    match serde::de::DeserializeSeed::deserialize(&registry, &mut de) {
+        Ok(_) => unreachable!(),
+        Err(err) => println!("{}", err),
+    }
+
+    // A message kind added by a newer peer is kept as `Unknown` and written
+    // back exactly as it was read: unsorted keys, spacing and the spelling
+    // of numbers included.
+    println!("Unknown variants:");
+    fn same<T: Serialize>(json: &str, message: &T) {
+        assert_eq!(serde_json::to_string(message).unwrap(), json);
+    }
+    let notification = r#"{"method": "PING",  "id": "7", "ttl": 1.0, "max": 1e3}"#;
+    let default = format!(r#"{{"Notification":{}}}"#, notification);
+    let message: MessageDefault = serde_json::from_str(&default).unwrap();
+    same(&default, &message);
+    println!("{:?}", message);
+
+    let internal = r#"{"type": "Notification", "ttl": 1.50,  "id": "7"}"#;
+    let message: MessageInternallyTagged = serde_json::from_str(internal).unwrap();
+    same(internal, &message);
+    if let MessageInternallyTagged::Unknown { tag, .. } = &message {
+        println!("Forwarding a {} unchanged", tag);
+    }
+
+    let adjacent = format!(r#"{{"t":"Notification","c":{}}}"#, notification);
+    let message: MessageAdjacentlyTagged = serde_json::from_str(&adjacent).unwrap();
+    same(&adjacent, &message);
+    println!("{:?}", message);
+    // Without content there is no `"c"` to write back.
+    let message: MessageAdjacentlyTagged = serde_json::from_str(r#"{"t": "Ping"}"#).unwrap();
+    assert_eq!(serde_json::to_string(&message).unwrap(), r#"{"t":"Ping"}"#);
+
+    let untagged = r#"{"ttl": 1.50,  "event": "ping", "at": -0.0}"#;
+    let message: MessageUntagged = serde_json::from_str(untagged).unwrap();
+    same(untagged, &message);
+    println!("{:?}", message);
+    // An object with a known field is a known variant, so a typo is an error.
+    let typo = r#"{"id": "1", "methd": "GET", "params": {}}"#;
+    let err = serde_json::from_str::<MessageUntagged>(typo).unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("expected an object with `method` or `result`"));
+    println!("{} => {}", typo, err);
+
+    // The raw text is only there when `serde_json` reads the message itself.
+    // An untagged parent buffers its input first, so a message inside one is
+    // read from a `Value`: known variants are the same, and an `Unknown` is
+    // written back from that `Value`.
+    #[derive(Deserialize, Debug)]
+    #[serde(untagged)]
+    enum Envelope {
+        Message(MessageInternallyTagged),
+        Other(serde_json::Value),
+    }
+    let json = r#"{"type": "Request", "id": "1", "method": "GET", "params": {"field_1": 30, "field_2": false, "field_3": []}}"#;
+    match serde_json::from_str(json).unwrap() {
+        Envelope::Message(MessageInternallyTagged::Request { id, .. }) => assert_eq!(id, "1"),
+        envelope => panic!("not read as a request: {:?}", envelope),
+    }
+    match serde_json::from_str(internal).unwrap() {
+        Envelope::Message(message @ MessageInternallyTagged::Unknown { .. }) => {
+            let written = serde_json::to_value(&message).unwrap();
+            let read: serde_json::Value = serde_json::from_str(internal).unwrap();
+            assert_eq!(written, read);
+        }
+        envelope => panic!("not read as unknown: {:?}", envelope),
+    }
+    match serde_json::from_str(r#"{"level": "info"}"#).unwrap() {
+        Envelope::Other(value) => assert_eq!(value["level"], "info"),
+        envelope => panic!("read as a message: {:?}", envelope),
+    }
+    // The same holds for a `Value`, which is read like any other format.
+    let value: serde_json::Value = serde_json::from_str(&default).unwrap();
+    let message: MessageDefault = serde_json::from_value(value.clone()).unwrap();
+    assert!(matches!(message, MessageDefault::Unknown { .. }));
+    assert_eq!(serde_json::to_value(&message).unwrap(), value);
+    let value: serde_json::Value = serde_json::from_str(json).unwrap();
+    assert!(matches!(
+        serde_json::from_value::<MessageInternallyTagged>(value).unwrap(),
+        MessageInternallyTagged::Request { .. }
+    ));
+    assert!(matches!(
+        serde_json::from_str::<MessageInternallyTagged>(json).unwrap(),
+        MessageInternallyTagged::Request { .. }
+    ));
+
+    // Errors in a known variant point into the original input.
+    let json = "[\n\n\n {\"Request\": {\"id\": 1}}]";
+    let err = serde_json::from_str::<Vec<MessageDefault>>(json).unwrap_err();
+    assert_eq!(err.line(), 4);
+    println!("{:?} => {}", json, err);
+
+    // Known variants are still checked as before.
+    let invalid = [
+        r#"{"type": "Request", "id": "1"}"#,
+        r#"{"type": 1, "id": "1"}"#,
+        r#"[]"#,
+    ];
+    for json in &invalid {
+        let err = serde_json::from_str::<MessageInternallyTagged>(json).unwrap_err();
+        println!("{} => {}", json, err);
+    }
+
+    // The same request in every representation, each detected as itself.
+    // Transcoding between the tagged ones gives the other's JSON. Only the
+    // untagged request has a `tuple`, so it cannot be transcoded to or from
+    // the others without losing it or making one up.
+    println!("Transcoding:");
+    let request: MessageDefault = MessageDefault::Request {
+        id: String::from("1"),
+        method: String::from("GET"),
+        params: Params::new(),
+    };
+    let default = serde_json::to_string(&request).unwrap();
+    let mut encodings: Vec<_> = Representation::ALL
+        .iter()
+        .filter(|&&to| to != Representation::Untagged)
+        .map(|&to| {
+            let json = convert::transcode(&default, Representation::Default, to).unwrap();
+            (to, json)
+        })
+        .collect();
+    let untagged: MessageUntagged = MessageUntagged::Request {
+        id: String::from("1"),
+        method: String::from("GET"),
+        tuple: (4, 3),
+        params: Params::new(),
+    };
+    encodings.push((
+        Representation::Untagged,
+        serde_json::to_string(&untagged).unwrap(),
+    ));
+    for (from, json) in &encodings {
+        println!("{}: {}", from, json);
+        let value: serde_json::Value = serde_json::from_str(json).unwrap();
+        assert_eq!(Representation::detect(&value), Some(*from));
+        for (to, expected) in &encodings {
+            match convert::transcode(json, *from, *to) {
+                Ok(json) => assert_eq!(&json, expected),
+                Err(err) => {
+                    assert!(
+                        (*from == Representation::Untagged) != (*to == Representation::Untagged)
+                    );
+                    println!("{} => {}: {}", from, to, err);
+                }
+            }
+        }
+    }
+
+    // `From` converts the enums directly, `Unknown` messages included.
+    let adjacent: MessageAdjacentlyTagged = request.into();
+    println!("{:?}", adjacent);
+    let unknown: MessageDefault =
+        serde_json::from_str(r#"{"Notification": {"id": "7", "ttl": 1.50}}"#).unwrap();
+    let internal = MessageInternallyTagged::from(unknown);
+    println!("{}", serde_json::to_string(&internal).unwrap());
+    let untagged = MessageUntagged::from(internal);
+    println!("{}", serde_json::to_string(&untagged).unwrap());
+
+    // An `Unknown` with content that is not an object transcodes to and from
+    // the adjacently tagged form unchanged. Internally tagged and untagged
+    // JSON could not be read back as the same message, so `transcode`
+    // refuses to write it.
+    let scalar = r#"{"Heartbeat":1.0}"#;
+    let adjacent = convert::transcode(
+        scalar,
+        Representation::Default,
+        Representation::AdjacentlyTagged,
+    )
+    .unwrap();
+    assert_eq!(adjacent, r#"{"t":"Heartbeat","c":1.0}"#);
+    let default = convert::transcode(
+        &adjacent,
+        Representation::AdjacentlyTagged,
+        Representation::Default,
+    )
+    .unwrap();
+    assert_eq!(default, scalar);
+    for &to in &[Representation::InternallyTagged, Representation::Untagged] {
+        let err = convert::transcode(scalar, Representation::Default, to).unwrap_err();
+        println!("{} => {}", to, err);
+    }
+    let internal =
+        MessageInternallyTagged::from(serde_json::from_str::<MessageDefault>(scalar).unwrap());
+    assert_eq!(
+        serde_json::to_string(&internal).unwrap(),
+        r#"{"type":"Heartbeat","content":1.0}"#
+    );
+
+    // The borrowed enums point into the input. Only strings with an escape
+    // sequence, like the `\/` in the method here, are copied.
+    println!("Borrowed:");
+    let json = r#"{"result": {"field_1": true, "field_2": "ok"}, "type": "Response", "id": "1"}"#;
+    let message: borrowed::MessageInternallyTagged = serde_json::from_str(json).unwrap();
+    if let borrowed::MessageInternallyTagged::Response(response) = &message {
+        assert!(matches!(response.id, Cow::Borrowed(_)));
+        assert_eq!(
+            response.result.get(),
+            r#"{"field_1": true, "field_2": "ok"}"#
+        );
+    }
+    println!("{:?}", message);
+
+    let json = r#"{"id": "1", "method": "files\/read", "tuple": [4, 3], "params": {"field_1": 30, "field_2": false, "field_3": []}}"#;
+    let message: borrowed::MessageUntagged = serde_json::from_str(json).unwrap();
+    if let borrowed::MessageUntagged::Request(request) = &message {
+        assert!(matches!(request.id, Cow::Borrowed(_)));
+        assert!(matches!(request.method, Cow::Owned(_)));
+    }
+    println!("{:?}", message);
+
+    let json = r#"{"t": "Request", "c": {"id": "1", "method": "GET", "params": {"field_1": 30, "field_2": false, "field_3": []}}}"#;
+    let message: borrowed::MessageAdjacentlyTagged = serde_json::from_str(json).unwrap();
+    let default: borrowed::MessageDefault = borrowed::MessageDefault::Request(match message {
+        borrowed::MessageAdjacentlyTagged::Request(request) => request,
+        borrowed::MessageAdjacentlyTagged::Response(_) => unreachable!(),
+    });
+    print_serialized(&default);
+
+    let invalid = [
+        r#"{"type": "Notification", "id": "1"}"#,
+        r#"{"type": "Request", "id": "1"}"#,
+        r#"{"id": "1"}"#,
+    ];
+    for json in &invalid {
+        let err = serde_json::from_str::<borrowed::MessageInternallyTagged>(json).unwrap_err();
+        println!("{} => {}", json, err);
+    }
 }
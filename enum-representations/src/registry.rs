@@ -0,0 +1,392 @@
+//! # Method registry
+//!
+//! A JSON-RPC `Request` carries its `params`, and a `Success` its `result`,
+//! as untyped JSON. What type they should have depends on the method, which
+//! a response does not even name. A `Method` ties a method name to its
+//! params and result types, and the two halves of a connection use it:
+//!
+//! - A `Registry` maps method names to handlers. It deserializes each
+//!   request's params into the type registered for its method before the
+//!   handler runs, and answers with the errors the specification reserves
+//!   for unknown methods and invalid params. A batch is answered with an
+//!   array of the responses to its elements.
+//! - A `Client` remembers the method of every request it sends by `id`, and
+//!   reads the matching response's result as that method's result type.
+
+use std::any::Any;
+use std::collections::HashMap;
+use std::fmt;
+
+use serde::de::{self, DeserializeOwned, DeserializeSeed, Deserializer};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::jsonrpc::{
+    ErrorCode, ErrorObject, Failure, Id, Message, Params, Payload, Request, Response, Success,
+    Version,
+};
+use crate::transport::Transport;
+
+/// A method that can be called, with the types of its params and result.
+pub trait Method {
+    const NAME: &'static str;
+
+    type Params: Serialize + DeserializeOwned + 'static;
+
+    type Result: Serialize + DeserializeOwned;
+}
+
+/// Reads params as the type of `M`, boxed so that entries of different
+/// methods fit in one map.
+type Parse = fn(Value) -> Result<Box<dyn Any>, serde_json::Error>;
+
+/// Runs the handler of a method on params that `Parse` produced.
+type Handler = Box<dyn Fn(Box<dyn Any>) -> Result<Value, ErrorObject>>;
+
+struct Entry {
+    parse: Parse,
+    handler: Handler,
+}
+
+fn parse<M: Method>(params: Value) -> Result<Box<dyn Any>, serde_json::Error> {
+    let params: M::Params = serde_json::from_value(params)?;
+    Ok(Box::new(params))
+}
+
+/// The handlers of a server, by method name.
+#[derive(Default)]
+pub struct Registry {
+    methods: HashMap<&'static str, Entry>,
+}
+
+/// A request whose params have been read as the type registered for its
+/// method, ready to be handled.
+pub struct Call<'r> {
+    /// `None` for a notification.
+    pub id: Option<Id>,
+    pub method: String,
+    params: Box<dyn Any>,
+    handler: &'r Handler,
+}
+
+impl Call<'_> {
+    /// The params, if this is a call to `M`.
+    pub fn params<M: Method>(&self) -> Option<&M::Params> {
+        if self.method == M::NAME {
+            self.params.downcast_ref()
+        } else {
+            None
+        }
+    }
+
+    pub fn run(self) -> Result<Value, ErrorObject> {
+        (self.handler)(self.params)
+    }
+}
+
+/// A `Success` with the result of a call, or a `Failure` with its error.
+fn response(id: Id, result: Result<Value, ErrorObject>) -> Response {
+    match result {
+        Ok(result) => Response::Success(Success {
+            jsonrpc: Version,
+            result,
+            id,
+        }),
+        Err(error) => Response::Failure(Failure {
+            jsonrpc: Version,
+            error,
+            id,
+        }),
+    }
+}
+
+fn to_string<T: Serialize>(response: &T) -> String {
+    serde_json::to_string(response).expect("a response always serializes")
+}
+
+/// An `ErrorObject` with one of the reserved codes and no `data`.
+fn error_object(code: ErrorCode, message: String) -> ErrorObject {
+    ErrorObject {
+        code,
+        message,
+        data: None,
+    }
+}
+
+impl Registry {
+    pub fn new() -> Self {
+        Registry::default()
+    }
+
+    /// Handles calls to `M` with `handler`, replacing any earlier handler.
+    pub fn register<M, F>(&mut self, handler: F) -> &mut Self
+    where
+        M: Method,
+        F: Fn(M::Params) -> Result<M::Result, ErrorObject> + 'static,
+    {
+        let handler = move |params: Box<dyn Any>| {
+            let params = params
+                .downcast::<M::Params>()
+                .expect("params are parsed by the same method");
+            let result = handler(*params)?;
+            serde_json::to_value(result)
+                .map_err(|err| error_object(ErrorCode::InternalError, err.to_string()))
+        };
+        self.methods.insert(
+            M::NAME,
+            Entry {
+                parse: parse::<M>,
+                handler: Box::new(handler),
+            },
+        );
+        self
+    }
+
+    /// Looks up the method of a request or notification and reads its params.
+    /// A failure comes with the `id` to answer it with.
+    fn call(&self, message: Message) -> Result<Call<'_>, (Option<Id>, ErrorObject)> {
+        let (id, method, params) = match message {
+            Message::Request(request) => (Some(request.id), request.method, request.params),
+            Message::Notification(notification) => (None, notification.method, notification.params),
+            Message::Response(_) => {
+                return Err((
+                    Some(Id::Null),
+                    error_object(ErrorCode::InvalidRequest, "expected a request".to_owned()),
+                ))
+            }
+        };
+        let entry = match self.methods.get(method.as_str()) {
+            Some(entry) => entry,
+            None => {
+                let message = format!("unknown method `{}`", method);
+                return Err((id, error_object(ErrorCode::MethodNotFound, message)));
+            }
+        };
+        let params = match params {
+            Some(Params::ByPosition(params)) => Value::Array(params),
+            Some(Params::ByName(params)) => Value::Object(params),
+            None => Value::Null,
+        };
+        match (entry.parse)(params) {
+            Ok(params) => Ok(Call {
+                id,
+                method,
+                params,
+                handler: &entry.handler,
+            }),
+            Err(err) => {
+                let message = format!("invalid params for `{}`: {}", method, err);
+                Err((id, error_object(ErrorCode::InvalidParams, message)))
+            }
+        }
+    }
+
+    /// Handles one payload, and returns the response to send back, if any.
+    /// Notifications are never answered, not even with an error. A batch is
+    /// answered with an array of the responses to its elements, and not at
+    /// all if they were all notifications.
+    pub fn handle(&self, json: &str) -> Option<String> {
+        let payload = match serde_json::from_str::<Value>(json) {
+            Ok(value) => {
+                Payload::deserialize(value).map_err(|err| (ErrorCode::InvalidRequest, err))
+            }
+            Err(err) => Err((ErrorCode::ParseError, err)),
+        };
+        match payload {
+            Ok(Payload::Single(message)) => {
+                self.respond(message).map(|response| to_string(&response))
+            }
+            Ok(Payload::Batch(elements)) => {
+                let responses: Vec<Response> = elements
+                    .into_iter()
+                    .filter_map(|element| match element {
+                        Ok(message) => self.respond(message),
+                        Err(invalid) => Some(response(
+                            Id::Null,
+                            Err(error_object(ErrorCode::InvalidRequest, invalid.error)),
+                        )),
+                    })
+                    .collect();
+                if responses.is_empty() {
+                    None
+                } else {
+                    Some(to_string(&responses))
+                }
+            }
+            Err((code, err)) => Some(to_string(&response(
+                Id::Null,
+                Err(error_object(code, err.to_string())),
+            ))),
+        }
+    }
+
+    /// The response to one message, or `None` for a notification.
+    fn respond(&self, message: Message) -> Option<Response> {
+        let (id, result) = match self.call(message) {
+            Ok(call) => (call.id.clone(), call.run()),
+            Err((id, error)) => (id, Err(error)),
+        };
+        id.map(|id| response(id, result))
+    }
+
+    /// Handles every message that has arrived on `transport`.
+    pub fn serve<T: Transport>(&self, transport: &mut T) {
+        while let Some(json) = transport.receive() {
+            if let Some(response) = self.handle(&json) {
+                transport.send(response);
+            }
+        }
+    }
+}
+
+/// Deserializes a request or notification into a `Call`, failing unless its
+/// method is registered and its params have the registered type.
+impl<'de, 'r> DeserializeSeed<'de> for &'r Registry {
+    type Value = Call<'r>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let message = Message::deserialize(deserializer)?;
+        self.call(message)
+            .map_err(|(_, error)| de::Error::custom(error.message))
+    }
+}
+
+/// Why a `Client` could not get a result.
+#[derive(Debug)]
+pub enum Error {
+    /// A message could not be written or read.
+    Json(serde_json::Error),
+    /// The transport has no more messages, but the response has not arrived.
+    Closed,
+    /// A response has an `id` that no pending request has.
+    UnknownId(Id),
+    /// The request with `id` called another method than the one its result
+    /// is read as.
+    WrongMethod {
+        id: Id,
+        called: &'static str,
+        expected: &'static str,
+    },
+    /// The server answered with an error.
+    Failed(ErrorObject),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Json(err) => write!(formatter, "{}", err),
+            Error::Closed => formatter.write_str("the connection closed before the response"),
+            Error::UnknownId(id) => write!(formatter, "no pending request has id {:?}", id),
+            Error::WrongMethod {
+                id,
+                called,
+                expected,
+            } => write!(
+                formatter,
+                "request {:?} called `{}`, not `{}`",
+                id, called, expected
+            ),
+            Error::Failed(error) => {
+                write!(formatter, "{} ({})", error.message, i64::from(error.code))
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Error::Json(err)
+    }
+}
+
+/// Sends requests and matches the responses to them by `id`.
+pub struct Client<T> {
+    transport: T,
+    next_id: i64,
+    /// The method of each request that has not been answered yet.
+    pending: HashMap<Id, &'static str>,
+    /// Responses that arrived before they were asked for.
+    arrived: HashMap<Id, Response>,
+}
+
+impl<T: Transport> Client<T> {
+    pub fn new(transport: T) -> Self {
+        Client {
+            transport,
+            next_id: 1,
+            pending: HashMap::new(),
+            arrived: HashMap::new(),
+        }
+    }
+
+    /// Sends a request to `M`, and returns the `id` to read its result with.
+    pub fn call<M: Method>(&mut self, params: &M::Params) -> Result<Id, Error> {
+        let id = Id::Number(self.next_id);
+        self.next_id += 1;
+        let request = Request {
+            jsonrpc: Version,
+            method: M::NAME.to_owned(),
+            params: to_params(params)?,
+            id: id.clone(),
+        };
+        self.transport.send(serde_json::to_string(&request)?);
+        self.pending.insert(id.clone(), M::NAME);
+        Ok(id)
+    }
+
+    /// Reads the result of the request with `id`, which must have been a
+    /// call to `M`. Responses to other requests that arrive first are kept
+    /// until they are asked for.
+    pub fn result<M: Method>(&mut self, id: &Id) -> Result<M::Result, Error> {
+        match self.pending.get(id) {
+            None => return Err(Error::UnknownId(id.clone())),
+            Some(&called) if called != M::NAME => {
+                return Err(Error::WrongMethod {
+                    id: id.clone(),
+                    called,
+                    expected: M::NAME,
+                })
+            }
+            Some(_) => {}
+        }
+        let response = loop {
+            if let Some(response) = self.arrived.remove(id) {
+                break response;
+            }
+            let json = self.transport.receive().ok_or(Error::Closed)?;
+            let response: Response = serde_json::from_str(&json)?;
+            let response_id = match &response {
+                Response::Success(success) => success.id.clone(),
+                Response::Failure(failure) => failure.id.clone(),
+            };
+            if !self.pending.contains_key(&response_id) {
+                return Err(Error::UnknownId(response_id));
+            }
+            self.arrived.insert(response_id, response);
+        };
+        self.pending.remove(id);
+        match response {
+            Response::Success(success) => Ok(serde_json::from_value(success.result)?),
+            Response::Failure(failure) => Err(Error::Failed(failure.error)),
+        }
+    }
+}
+
+/// Writes params as JSON-RPC params, which must be an array or an object,
+/// or nothing at all.
+fn to_params<P: Serialize>(params: &P) -> Result<Option<Params>, serde_json::Error> {
+    match serde_json::to_value(params)? {
+        Value::Array(params) => Ok(Some(Params::ByPosition(params))),
+        Value::Object(params) => Ok(Some(Params::ByName(params))),
+        Value::Null => Ok(None),
+        other => Err(serde::ser::Error::custom(format!(
+            "params must be an array or an object, not {}",
+            other
+        ))),
+    }
+}
@@ -0,0 +1,98 @@
+//! # Typed results
+//!
+//! A response does not say which method it answers, so the type of its
+//! `result` is not known while it is being read. The `Message*` enums are
+//! generic over that type, `R`, which defaults to `serde_json::Value`. A
+//! response is read with the dynamic result first, and once the request it
+//! answers is known, `decode_result` decodes the result into the type that
+//! the request's method returns.
+
+use crate::{
+    MessageAdjacentlyTagged, MessageDefault, MessageInternallyTagged, MessageNumericTagged,
+    MessageUntagged,
+};
+
+/// The results of a set of methods, usually an enum with a variant for each
+/// method.
+pub trait MethodResult: Sized {
+    /// Decodes the `result` of a call to `method`.
+    fn decode(method: &str, result: serde_json::Value) -> Result<Self, serde_json::Error>;
+}
+
+/// Adds `map_result` to each enum. Every variant other than `Response` is
+/// listed with its fields and kept as it is.
+macro_rules! map_result {
+    ($($message:ident { $($variant:ident { $($field:ident),* })* })*) => {
+        $(
+            impl<R> $message<R> {
+                /// Converts the result of a `Response` with `f`. Other messages
+                /// are kept as they are.
+                pub fn map_result<T, E, F>(self, f: F) -> Result<$message<T>, E>
+                where
+                    F: FnOnce(R) -> Result<T, E>,
+                {
+                    Ok(match self {
+                        $message::Response { id, result } => $message::Response {
+                            id,
+                            result: f(result)?,
+                        },
+                        $(
+                            $message::$variant { $($field),* } => {
+                                $message::$variant { $($field),* }
+                            }
+                        )*
+                    })
+                }
+            }
+        )*
+    };
+}
+
+map_result! {
+    MessageDefault {
+        Request { id, method, params }
+        Unknown { tag, raw }
+    }
+    MessageInternallyTagged {
+        Request { id, method, params }
+        Unknown { tag, raw }
+    }
+    MessageNumericTagged {
+        Request { id, method, params }
+    }
+    MessageAdjacentlyTagged {
+        Request { id, method, params }
+        Unknown { tag, raw }
+    }
+    MessageUntagged {
+        Request { id, method, tuple, params }
+        Unknown { raw }
+    }
+}
+
+/// Adds `decode_result` to each enum, on top of its `map_result`.
+macro_rules! decode_result {
+    ($($message:ident)*) => {
+        $(
+            impl $message {
+                /// Decodes the result of a `Response` into the result type of
+                /// `method`, the method of the request it answers. Other
+                /// messages are kept as they are.
+                pub fn decode_result<T: MethodResult>(
+                    self,
+                    method: &str,
+                ) -> Result<$message<T>, serde_json::Error> {
+                    self.map_result(|result| T::decode(method, result))
+                }
+            }
+        )*
+    };
+}
+
+decode_result! {
+    MessageDefault
+    MessageInternallyTagged
+    MessageNumericTagged
+    MessageAdjacentlyTagged
+    MessageUntagged
+}
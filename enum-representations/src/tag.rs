@@ -0,0 +1,147 @@
+//! # Non-string tags
+//!
+//! `#[serde(tag = "type")]` only accepts the name of a variant as the tag.
+//! Some producers tag their messages with integers or booleans instead, or
+//! have used several spellings over time. A `Tagged` enum lists the tag
+//! values of each of its variants, and `serialize` and `deserialize` handle
+//! the internally tagged form from there.
+//!
+//! Like `#[serde(tag = "...")]`, this works for struct variants and newtype
+//! variants holding a struct or a map.
+//!
+//! `MessageNumericTagged` is one such enum. Another enum implements `Tagged`
+//! the same way, with a remote definition for the externally tagged form,
+//! and calls `tag::serialize` and `tag::deserialize` from its own impls.
+
+use std::fmt;
+
+use serde::de::{self, Deserialize, Deserializer};
+use serde::ser::{self, SerializeMap, Serializer};
+use serde_json::{Map, Value};
+
+/// A tag value.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Tag {
+    Int(i64),
+    Bool(bool),
+    Str(&'static str),
+}
+
+impl Tag {
+    fn matches(&self, value: &Value) -> bool {
+        match (self, value) {
+            (Tag::Int(tag), Value::Number(number)) => number.as_i64() == Some(*tag),
+            (Tag::Bool(tag), Value::Bool(value)) => tag == value,
+            (Tag::Str(tag), Value::String(value)) => tag == value,
+            _ => false,
+        }
+    }
+
+    fn to_value(self) -> Value {
+        match self {
+            Tag::Int(tag) => Value::from(tag),
+            Tag::Bool(tag) => Value::from(tag),
+            Tag::Str(tag) => Value::from(tag),
+        }
+    }
+}
+
+impl fmt::Display for Tag {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Tag::Int(tag) => write!(formatter, "{}", tag),
+            Tag::Bool(tag) => write!(formatter, "{}", tag),
+            Tag::Str(tag) => write!(formatter, "{:?}", tag),
+        }
+    }
+}
+
+/// The tag values of one variant. The first one is written when
+/// serializing, and all of them are accepted when deserializing.
+pub struct Variant {
+    pub name: &'static str,
+    pub tags: &'static [Tag],
+}
+
+/// An enum that is internally tagged with the values in `VARIANTS`.
+///
+/// The enum is converted from and to its externally tagged form,
+/// `{"Variant": {...}}`, which a remote definition of the enum can derive.
+pub trait Tagged: Sized {
+    /// The key that holds the tag, like `tag` in `#[serde(tag = "...")]`.
+    const TAG: &'static str;
+
+    const VARIANTS: &'static [Variant];
+
+    fn to_externally_tagged(&self) -> Result<Value, serde_json::Error>;
+
+    fn from_externally_tagged(value: Value) -> Result<Self, serde_json::Error>;
+}
+
+/// Writes the content of the variant with its first tag value added under
+/// `T::TAG`.
+pub fn serialize<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: Tagged,
+    S: Serializer,
+{
+    let (name, content) = match value.to_externally_tagged().map_err(ser::Error::custom)? {
+        Value::Object(object) if object.len() == 1 => object.into_iter().next().unwrap(),
+        _ => return Err(ser::Error::custom("expected an externally tagged variant")),
+    };
+    let content = match content {
+        Value::Object(content) => content,
+        _ => {
+            return Err(ser::Error::custom(format!(
+                "variant {} cannot be internally tagged, its content is not a map",
+                name
+            )))
+        }
+    };
+    let tag = T::VARIANTS
+        .iter()
+        .find(|variant| variant.name == name)
+        .and_then(|variant| variant.tags.first())
+        .ok_or_else(|| ser::Error::custom(format!("variant {} has no tag", name)))?;
+
+    let mut map = serializer.serialize_map(Some(content.len() + 1))?;
+    map.serialize_entry(T::TAG, &tag.to_value())?;
+    for (key, value) in &content {
+        map.serialize_entry(key, value)?;
+    }
+    map.end()
+}
+
+/// Reads the tag under `T::TAG`, and deserializes the rest of the object as
+/// the variant that tag belongs to.
+pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+where
+    T: Tagged,
+    D: Deserializer<'de>,
+{
+    let mut content = Map::deserialize(deserializer)?;
+    let tag = content
+        .remove(T::TAG)
+        .ok_or_else(|| de::Error::missing_field(T::TAG))?;
+    let variant = T::VARIANTS
+        .iter()
+        .find(|variant| variant.tags.iter().any(|known| known.matches(&tag)))
+        .ok_or_else(|| {
+            let known: Vec<_> = T::VARIANTS
+                .iter()
+                .flat_map(|variant| variant.tags)
+                .map(Tag::to_string)
+                .collect();
+            de::Error::custom(format!(
+                "unknown tag {} in `{}`, expected one of {}",
+                tag,
+                T::TAG,
+                known.join(", ")
+            ))
+        })?;
+
+    let mut tagged = Map::new();
+    tagged.insert(variant.name.to_owned(), Value::Object(content));
+    T::from_externally_tagged(Value::Object(tagged))
+        .map_err(|err| de::Error::custom(format!("invalid {}: {}", variant.name, err)))
+}
@@ -0,0 +1,55 @@
+//! # Transports
+//!
+//! A `Transport` moves whole JSON messages between a client and a server.
+//! `Memory` connects the two within one process, which is enough to run a
+//! `registry::Client` against a `registry::Registry` without any I/O.
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+/// One end of a connection.
+pub trait Transport {
+    fn send(&mut self, message: String);
+
+    /// The next message that has arrived, or `None` if there is none yet.
+    fn receive(&mut self) -> Option<String>;
+}
+
+type Queue = Rc<RefCell<VecDeque<String>>>;
+
+/// One end of an in-memory connection. Messages are delivered in the order
+/// they were sent.
+#[derive(Debug)]
+pub struct Memory {
+    incoming: Queue,
+    outgoing: Queue,
+}
+
+impl Memory {
+    /// Two connected ends: what one sends, the other receives.
+    pub fn pair() -> (Memory, Memory) {
+        let a = Queue::default();
+        let b = Queue::default();
+        (
+            Memory {
+                incoming: a.clone(),
+                outgoing: b.clone(),
+            },
+            Memory {
+                incoming: b,
+                outgoing: a,
+            },
+        )
+    }
+}
+
+impl Transport for Memory {
+    fn send(&mut self, message: String) {
+        self.outgoing.borrow_mut().push_back(message);
+    }
+
+    fn receive(&mut self) -> Option<String> {
+        self.incoming.borrow_mut().pop_front()
+    }
+}
@@ -0,0 +1,218 @@
+//! Companions to `display_from_str` for containers of `FromStr + Display`
+//! types.
+//!
+//! Each module is meant for `#[serde(with = "...")]`. Like `Outer::s`, a
+//! generic field using one of them has to replace the bounds that Serde
+//! would generate with the ones the module needs, e.g.
+//!
+//! ```ignore
+//! #[serde(with = "adapters::vec_display_from_str")]
+//! #[serde(bound(serialize = "A: Display", deserialize = "A: FromStr, A::Err: Display"))]
+//! peers: Vec<A>,
+//! ```
+
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Serializes the value it points to with `serialize_display`, so that it can
+/// be handed to `serialize_some`, `collect_seq` and `collect_map`.
+struct AsDisplay<'a, S>(&'a S);
+
+impl<'a, S: Display> Serialize for AsDisplay<'a, S> {
+    fn serialize<Ser>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error>
+    where
+        Ser: Serializer,
+    {
+        crate::serialize_display(self.0, serializer)
+    }
+}
+
+/// Deserializes a value with `deserialize_from_str`, so that it can be used
+/// as the element type of `Option`, `Vec` and maps.
+struct Parsed<S>(S);
+
+impl<'de, S> Deserialize<'de> for Parsed<S>
+where
+    S: FromStr,
+    S::Err: Display,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        crate::deserialize_from_str(deserializer).map(Parsed)
+    }
+}
+
+/// `Option<S>`, where `None` is `null` and `Some` is the string form of `S`.
+pub mod option_display_from_str {
+    use super::*;
+
+    pub fn serialize<S, Ser>(value: &Option<S>, serializer: Ser) -> Result<Ser::Ok, Ser::Error>
+    where
+        S: Display,
+        Ser: Serializer,
+    {
+        match value {
+            Some(value) => serializer.serialize_some(&AsDisplay(value)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, S, D>(deserializer: D) -> Result<Option<S>, D::Error>
+    where
+        S: FromStr,
+        S::Err: Display,
+        D: Deserializer<'de>,
+    {
+        let parsed: Option<Parsed<S>> = Deserialize::deserialize(deserializer)?;
+        Ok(parsed.map(|Parsed(value)| value))
+    }
+}
+
+/// `Vec<S>`, as a sequence of the string forms of `S`.
+pub mod vec_display_from_str {
+    use super::*;
+
+    pub fn serialize<S, Ser>(values: &[S], serializer: Ser) -> Result<Ser::Ok, Ser::Error>
+    where
+        S: Display,
+        Ser: Serializer,
+    {
+        serializer.collect_seq(values.iter().map(AsDisplay))
+    }
+
+    pub fn deserialize<'de, S, D>(deserializer: D) -> Result<Vec<S>, D::Error>
+    where
+        S: FromStr,
+        S::Err: Display,
+        D: Deserializer<'de>,
+    {
+        let parsed: Vec<Parsed<S>> = Deserialize::deserialize(deserializer)?;
+        Ok(parsed.into_iter().map(|Parsed(value)| value).collect())
+    }
+}
+
+/// Any map type, such as `HashMap<K, V>` or `BTreeMap<K, V>`, where both the
+/// keys and the values are written in their string forms.
+pub mod map_display_from_str {
+    use std::iter::FromIterator;
+    use std::marker::PhantomData;
+
+    use serde::de::{MapAccess, Visitor};
+
+    use super::*;
+
+    pub fn serialize<M, K, V, Ser>(map: &M, serializer: Ser) -> Result<Ser::Ok, Ser::Error>
+    where
+        for<'m> &'m M: IntoIterator<Item = (&'m K, &'m V)>,
+        K: Display,
+        V: Display,
+        Ser: Serializer,
+    {
+        serializer.collect_map(
+            map.into_iter()
+                .map(|(key, value)| (AsDisplay(key), AsDisplay(value))),
+        )
+    }
+
+    pub fn deserialize<'de, M, K, V, D>(deserializer: D) -> Result<M, D::Error>
+    where
+        M: FromIterator<(K, V)>,
+        K: FromStr,
+        K::Err: Display,
+        V: FromStr,
+        V::Err: Display,
+        D: Deserializer<'de>,
+    {
+        struct MapVisitor<M, K, V>(PhantomData<(M, K, V)>);
+
+        impl<'de, M, K, V> Visitor<'de> for MapVisitor<M, K, V>
+        where
+            M: FromIterator<(K, V)>,
+            K: FromStr,
+            K::Err: Display,
+            V: FromStr,
+            V::Err: Display,
+        {
+            type Value = M;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a map of strings")
+            }
+
+            fn visit_map<A>(self, mut access: A) -> Result<M, A::Error>
+            where
+                A: MapAccess<'de>,
+            {
+                let mut entries = Vec::with_capacity(access.size_hint().unwrap_or(0));
+                while let Some((Parsed(key), Parsed(value))) = access.next_entry()? {
+                    entries.push((key, value));
+                }
+                Ok(entries.into_iter().collect())
+            }
+        }
+
+        deserializer.deserialize_map(MapVisitor(PhantomData))
+    }
+}
+
+/// `Vec<S>`, as a single string of comma-separated string forms of `S`, such
+/// as `"a,b,c"`. Whitespace around each item is ignored and the empty string
+/// is the empty `Vec`.
+///
+/// So that what is written reads back the same, serializing fails for an
+/// item whose string form contains a `,` or starts or ends with whitespace,
+/// and for a single item whose string form is empty.
+pub mod comma_separated {
+    use serde::ser;
+
+    use super::*;
+
+    pub fn serialize<S, Ser>(values: &[S], serializer: Ser) -> Result<Ser::Ok, Ser::Error>
+    where
+        S: Display,
+        Ser: Serializer,
+    {
+        let items: Vec<String> = values.iter().map(ToString::to_string).collect();
+        for item in &items {
+            if item.contains(',') {
+                return Err(ser::Error::custom(format!(
+                    "cannot write {:?} in a comma-separated list, it contains a `,`",
+                    item
+                )));
+            }
+            if item.trim() != item {
+                return Err(ser::Error::custom(format!(
+                    "cannot write {:?} in a comma-separated list, it starts or ends with whitespace",
+                    item
+                )));
+            }
+        }
+        if let [item] = &items[..] {
+            if item.is_empty() {
+                return Err(ser::Error::custom(
+                    "cannot write a single empty item as a comma-separated list, it reads back as no items",
+                ));
+            }
+        }
+        serializer.serialize_str(&items.join(","))
+    }
+
+    pub fn deserialize<'de, S, D>(deserializer: D) -> Result<Vec<S>, D::Error>
+    where
+        S: FromStr,
+        S::Err: Display,
+        D: Deserializer<'de>,
+    {
+        let s: String = Deserialize::deserialize(deserializer)?;
+        if s.trim().is_empty() {
+            return Ok(Vec::new());
+        }
+        s.split(',')
+            .map(|item| crate::parse_from_str(item.trim()).map_err(crate::from_str_error))
+            .collect()
+    }
+}
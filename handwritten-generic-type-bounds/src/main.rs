@@ -10,33 +10,59 @@
 //! an escape hatch to replace the automatically generated bound by one written
 //! by the programmer.
 
-use serde::{de, Deserialize, Deserializer};
+mod adapters;
 
-use std::fmt::{Debug, Display};
+use serde::de::{self, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::ffi::{CStr, CString, OsStr, OsString};
+use std::fmt::{self, Display};
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
 /// A struct that owns a element of type `S` and points to an element of type
 /// `T`.
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug)]
 struct Outer<'a, S, T: 'a + ?Sized> {
     /// When deriving the `Deserialize`, Serde would want to generate a
     /// bound `S: Deserialize` on the type of this field, but we are going to
     /// use type's `FromStr` impl instead of its `Deserialize` impl by going
-    /// `deserialize_from_str`, so we override the automatically generated
-    /// bound by the one required for `deserialize_from_str`.
-    #[serde(deserialize_with = "deserialize_from_str")]
-    #[serde(bound(deserialize = "S: FromStr, S::Err: Display"))]
+    /// through `display_from_str`, so we override the automatically generated
+    /// bounds by the ones required for `display_from_str`. The same goes for
+    /// `Serialize`, which is replaced by the type's `Display` impl.
+    #[serde(with = "display_from_str")]
+    #[serde(bound(serialize = "S: Display", deserialize = "S: FromStr, S::Err: Display"))]
     s: S,
 
     /// Here, Serde uses a stricter condition than necessary: `T: Deserialize`.
     /// This prevents `T=str` since `str` does not implement `Deserialize`. We
     /// override the automically generated bound with a looser one. Now, only
     /// the pointer to the `str` need to implement `Deserialize`, rather than
-    /// the `str` itself.
-    #[serde(bound(deserialize = "Ptr<'a, T>: Deserialize<'de>"))]
+    /// the `str` itself. `Serialize` is not a problem for `str`, but we
+    /// loosen that bound the same way for symmetry.
+    #[serde(bound(
+        serialize = "Ptr<'a, T>: Serialize",
+        deserialize = "Ptr<'a, T>: Deserialize<'de>"
+    ))]
     ptr: Ptr<'a, T>,
 }
 
+/// Serialize a type `S` as a string by using the `Display` impl of `S`. The
+/// generic type `S` is not required to implement `Serialize`.
+fn serialize_display<S, Ser>(value: &S, serializer: Ser) -> Result<Ser::Ok, Ser::Error>
+where
+    S: Display,
+    Ser: Serializer,
+{
+    // Writes the `Display` output straight into the serializer, without
+    // allocating an intermediate `String` where the format allows it.
+    serializer.collect_str(value)
+}
+
 /// Deserialize a type `S` by deserializing a string, then using the `FromStr`
 /// impl of `S` to create the result. The generic type `S` is not required to
 /// implement `Deserialize`.
@@ -50,40 +76,526 @@ where
     let s: String = Deserialize::deserialize(deserializer)?;
     // Attempts to convert the deserialized string to type `S`.
     // Calls the deserializer's `custom` method if the parsing fails.
-    S::from_str(&s).map_err(de::Error::custom)
+    parse_from_str(&s).map_err(from_str_error)
+}
+
+/// Turns a `FromStrError` into the error of a `Deserializer`, and keeps the
+/// structured error aside for `from_json`. Every parse failure inside a
+/// `Deserialize` impl should go through here.
+fn from_str_error<E: de::Error>(err: FromStrError) -> E {
+    let message = E::custom(&err);
+    LAST_FROM_STR_ERROR.with(|last| *last.borrow_mut() = Some(err));
+    message
+}
+
+thread_local! {
+    /// The last `FromStrError` passed to `from_str_error` on this thread. A
+    /// `Deserializer` only keeps the message of a custom error, so
+    /// `from_json` takes the structured error from here instead.
+    static LAST_FROM_STR_ERROR: RefCell<Option<FromStrError>> = const { RefCell::new(None) };
+}
+
+/// Either a `FromStr` failure or any other `serde_json` error.
+#[derive(Debug)]
+enum JsonError {
+    FromStr(FromStrError),
+    Json(serde_json::Error),
+}
+
+impl Display for JsonError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            JsonError::FromStr(err) => Display::fmt(err, formatter),
+            JsonError::Json(err) => Display::fmt(err, formatter),
+        }
+    }
+}
+
+impl std::error::Error for JsonError {}
+
+/// Deserializes a `T` from `json` like `serde_json::from_str`, but returns
+/// the `FromStrError` as is when a `FromStr` parse is what failed.
+fn from_json<'de, T>(json: &'de str) -> Result<T, JsonError>
+where
+    T: Deserialize<'de>,
+{
+    LAST_FROM_STR_ERROR.with(|last| last.borrow_mut().take());
+    serde_json::from_str(json).map_err(|err| {
+        // A `FromStr` failure that was recovered from, e.g. by an untagged
+        // enum trying another variant, is not what made `json` fail, so the
+        // error is only used if it is the one that was returned.
+        match LAST_FROM_STR_ERROR.with(|last| last.borrow_mut().take()) {
+            Some(from_str) if err.to_string().starts_with(&from_str.to_string()) => {
+                JsonError::FromStr(from_str)
+            }
+            _ => JsonError::Json(err),
+        }
+    })
+}
+
+/// Uses the `FromStr` impl of `S` to parse `s`, keeping enough context in
+/// the error to tell what went wrong.
+fn parse_from_str<S>(s: &str) -> Result<S, FromStrError>
+where
+    S: FromStr,
+    S::Err: Display,
+{
+    S::from_str(s).map_err(|err| FromStrError::new::<S>(s, err))
+}
+
+/// A `FromStr` failure together with the input and the type it was parsed
+/// as. `Display` gives a message for the `Deserializer` error, and
+/// `Serialize` gives a structured error that an API can return as is.
+///
+/// A `Deserializer` only keeps the message, so `from_json` returns the
+/// structured error for JSON input.
+#[derive(Serialize, Debug, PartialEq)]
+struct FromStrError {
+    /// The input, truncated to `FromStrError::MAX_INPUT_CHARS` characters.
+    input: String,
+    /// The `std::any::type_name` of the type that failed to parse.
+    target: Cow<'static, str>,
+    /// The message of the `FromStr` error.
+    cause: String,
+}
+
+impl FromStrError {
+    /// Long inputs are cut off, since the error should point at the input
+    /// rather than repeat it.
+    const MAX_INPUT_CHARS: usize = 32;
+
+    fn new<S>(input: &str, cause: impl Display) -> Self {
+        let mut truncated: String = input.chars().take(Self::MAX_INPUT_CHARS).collect();
+        if truncated.len() < input.len() {
+            truncated.push_str("...");
+        }
+        FromStrError {
+            input: truncated,
+            target: Cow::Borrowed(std::any::type_name::<S>()),
+            cause: cause.to_string(),
+        }
+    }
+}
+
+impl Display for FromStrError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        let input = serde_json::to_string(&self.input).map_err(|_| fmt::Error)?;
+        write!(
+            formatter,
+            "cannot parse {} as `{}`: {}",
+            input, self.target, self.cause
+        )
+    }
+}
+
+impl std::error::Error for FromStrError {}
+
+/// Pairs `serialize_display` with `deserialize_from_str` so that a field of
+/// any `FromStr + Display` type can be written back in the form it was read,
+/// using `#[serde(with = "display_from_str")]`.
+mod display_from_str {
+    pub(super) use super::deserialize_from_str as deserialize;
+    pub(super) use super::serialize_display as serialize;
 }
 
-/// A pointer to `T` which may or may not own the data. When deserializing, we
-/// always want to produce owned data.
+/// A configuration where every `A` and `W` is written as a string. The
+/// adapters in `adapters` extend `display_from_str` to containers, and need
+/// their bounds replaced in the same way.
+#[derive(Serialize, Deserialize, Debug)]
+struct Config<A: Ord, W> {
+    #[serde(with = "adapters::option_display_from_str")]
+    #[serde(bound(serialize = "A: Display", deserialize = "A: FromStr, A::Err: Display"))]
+    listen: Option<A>,
+
+    #[serde(with = "adapters::vec_display_from_str")]
+    #[serde(bound(serialize = "A: Display", deserialize = "A: FromStr, A::Err: Display"))]
+    peers: Vec<A>,
+
+    /// The map adapter additionally needs the key to be `Ord`, which is
+    /// already required by the struct itself.
+    #[serde(with = "adapters::map_display_from_str")]
+    #[serde(bound(
+        serialize = "A: Display, W: Display",
+        deserialize = "A: FromStr, A::Err: Display, W: FromStr, W::Err: Display"
+    ))]
+    weights: BTreeMap<A, W>,
+
+    #[serde(with = "adapters::comma_separated")]
+    #[serde(bound(serialize = "A: Display", deserialize = "A: FromStr, A::Err: Display"))]
+    blocked: Vec<A>,
+}
+
+/// A pointer to `T` which may or may not own the data. Like `Cow`, it
+/// borrows from the input when it can and owns the data otherwise.
 #[derive(Debug)]
 enum Ptr<'a, T: 'a + ?Sized> {
     Ref(&'a T),
     Owned(Box<T>),
 }
 
-impl<'de, 'a, T: 'a + ?Sized> Deserialize<'de> for Ptr<'a, T>
+/// Both variants serialize as the value they point to, so a borrowed and an
+/// owned `Ptr` to equal values produce the same output.
+impl<'a, T: 'a + ?Sized> Serialize for Ptr<'a, T>
+where
+    T: Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            Ptr::Ref(value) => value.serialize(serializer),
+            Ptr::Owned(value) => value.serialize(serializer),
+        }
+    }
+}
+
+/// A sized `T` can never be borrowed from the input, so it is always
+/// deserialized into an owned box.
+impl<'de, 'a, T: 'a> Deserialize<'de> for Ptr<'a, T>
 where
-    Box<T>: Deserialize<'de> + Debug,
+    T: Deserialize<'de>,
 {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: Deserializer<'de>,
     {
-        Deserialize::deserialize(deserializer).map(|e| {
-            println!("Deserialized first to Boxed({:?})", e);
-            Ptr::Owned(e)
-        })
+        Box::deserialize(deserializer).map(Ptr::Owned)
+    }
+}
+
+/// Borrows the string from the input when the `Deserializer` offers
+/// `visit_borrowed_str`, which `serde_json::from_str` does for strings
+/// without escape sequences. Falls back to an owned copy otherwise.
+impl<'de: 'a, 'a> Deserialize<'de> for Ptr<'a, str> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct StrVisitor;
+
+        impl<'de> Visitor<'de> for StrVisitor {
+            type Value = Ptr<'de, str>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a string")
+            }
+
+            fn visit_borrowed_str<E>(self, value: &'de str) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                Ok(Ptr::Ref(value))
+            }
+
+            fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                Ok(Ptr::Owned(value.into()))
+            }
+
+            fn visit_string<E>(self, value: String) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                Ok(Ptr::Owned(value.into_boxed_str()))
+            }
+        }
+
+        deserializer.deserialize_str(StrVisitor)
+    }
+}
+
+/// Borrows the bytes from the input when the `Deserializer` offers
+/// `visit_borrowed_bytes` or `visit_borrowed_str`. Falls back to an owned
+/// copy otherwise, including for sequences of integers, which is how JSON
+/// represents bytes.
+impl<'de: 'a, 'a> Deserialize<'de> for Ptr<'a, [u8]> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct BytesVisitor;
+
+        impl<'de> Visitor<'de> for BytesVisitor {
+            type Value = Ptr<'de, [u8]>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a byte array")
+            }
+
+            fn visit_borrowed_bytes<E>(self, value: &'de [u8]) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                Ok(Ptr::Ref(value))
+            }
+
+            fn visit_borrowed_str<E>(self, value: &'de str) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                Ok(Ptr::Ref(value.as_bytes()))
+            }
+
+            fn visit_bytes<E>(self, value: &[u8]) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                Ok(Ptr::Owned(value.into()))
+            }
+
+            fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                Ok(Ptr::Owned(value.as_bytes().into()))
+            }
+
+            fn visit_byte_buf<E>(self, value: Vec<u8>) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                Ok(Ptr::Owned(value.into_boxed_slice()))
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+            where
+                A: SeqAccess<'de>,
+            {
+                let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+                while let Some(byte) = seq.next_element()? {
+                    bytes.push(byte);
+                }
+                Ok(Ptr::Owned(bytes.into_boxed_slice()))
+            }
+        }
+
+        deserializer.deserialize_bytes(BytesVisitor)
     }
 }
 
+/// Deserializes a `Ptr` to any `T` that a `Box<T>` can be deserialized into,
+/// always as `Ptr::Owned`. This is the generic path for the types that
+/// `Ptr` has no `Deserialize` impl for, such as `[(u8, u8)]` or a slice of
+/// a user type, by using `#[serde(deserialize_with = "owned_ptr")]` with a
+/// `Box<T>: Deserialize<'de>` bound.
+fn owned_ptr<'de, 'a, T, D>(deserializer: D) -> Result<Ptr<'a, T>, D::Error>
+where
+    T: 'a + ?Sized,
+    Box<T>: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    Box::deserialize(deserializer).map(Ptr::Owned)
+}
+
+/// Other unsized types cannot be borrowed from the input, and are always
+/// deserialized into an owned box, through the owned type that the box is
+/// made from. `[u8]` borrows, so a blanket impl for every `[T]` would
+/// overlap with it. Instead, slices of the common element types get an impl
+/// here, and every other `T` goes through `owned_ptr`.
+macro_rules! owned_ptr {
+    ($($unsized:ty => $owned:ty, $into_boxed:path;)*) => {
+        $(
+            impl<'de, 'a> Deserialize<'de> for Ptr<'a, $unsized> {
+                fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+                where
+                    D: Deserializer<'de>,
+                {
+                    <$owned>::deserialize(deserializer).map(|owned| Ptr::Owned($into_boxed(owned)))
+                }
+            }
+        )*
+    };
+    ($([$element:ty])*) => {
+        owned_ptr! {
+            $([$element] => Vec<$element>, Vec::into_boxed_slice;)*
+        }
+    };
+}
+
+owned_ptr! {
+    Path => PathBuf, PathBuf::into_boxed_path;
+    OsStr => OsString, OsString::into_boxed_os_str;
+    CStr => CString, CString::into_boxed_c_str;
+}
+
+owned_ptr! {
+    [bool] [char] [String]
+    [i8] [i16] [i32] [i64] [i128] [isize]
+    [u16] [u32] [u64] [u128] [usize]
+    [f32] [f64]
+}
+
+/// A port given by number or by service name. Trying `Number` first makes a
+/// `FromStr` failure that `Name` then recovers from.
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+enum Port {
+    Number(#[serde(deserialize_with = "deserialize_from_str")] u16),
+    Name(String),
+}
+
+/// A struct pointing to slices that `Ptr` has no `Deserialize` impl for.
+/// `deserialize_with` keeps Serde from generating a `Ptr: Deserialize`
+/// bound for these fields.
+#[derive(Deserialize, Debug)]
+struct Shape<'a> {
+    #[serde(deserialize_with = "owned_ptr")]
+    points: Ptr<'a, [(u8, u8)]>,
+
+    #[serde(deserialize_with = "owned_ptr")]
+    labels: Ptr<'a, [Option<u32>]>,
+}
+
 fn main() {
+    // Without escape sequences, the string can be borrowed straight out of
+    // `json`.
     let json = r#"
         {
             "s": "1234567890",
-            "ptr": "owned"
+            "ptr": "borrowed"
         }
     "#;
+    let result: Outer<u64, str> = serde_json::from_str(json).unwrap();
+    println!("result = {:?}", result);
 
+    // The escape sequence has to be decoded, so the string is owned.
+    let json = r#"
+        {
+            "s": "1234567890",
+            "ptr": "owned\n"
+        }
+    "#;
     let result: Outer<u64, str> = serde_json::from_str(json).unwrap();
     println!("result = {:?}", result);
+
+    // A reader cannot lend out its buffer, so the string is owned.
+    let json = br#"{"s": "1234567890", "ptr": "owned"}"#;
+    let mut deserializer = serde_json::Deserializer::from_reader(&json[..]);
+    let result = Outer::<u64, str>::deserialize(&mut deserializer).unwrap();
+    println!("result = {:?}", result);
+
+    // Bytes borrow from a string, but are owned when given as an array.
+    let bytes: Ptr<[u8]> = serde_json::from_str(r#""bytes""#).unwrap();
+    println!("bytes  = {:?}", bytes);
+    let bytes: Ptr<[u8]> = serde_json::from_str("[98, 121, 116, 101, 115]").unwrap();
+    println!("bytes  = {:?}", bytes);
+
+    // Other unsized types are always owned.
+    let numbers: Ptr<[u32]> = serde_json::from_str("[1, 2, 3]").unwrap();
+    println!("numbers = {:?}", numbers);
+    assert!(matches!(numbers, Ptr::Owned(ref numbers) if **numbers == [1, 2, 3]));
+    let path: Ptr<Path> = serde_json::from_str(r#""/tmp/file""#).unwrap();
+    println!("path   = {:?}", path);
+    assert!(matches!(path, Ptr::Owned(ref path) if **path == *Path::new("/tmp/file")));
+    // Serde writes an `OsStr` tagged with the platform it comes from.
+    let os_str: Ptr<OsStr> = serde_json::from_str(r#"{"Unix": [104, 105]}"#).unwrap();
+    println!("os_str = {:?}", os_str);
+    assert!(matches!(os_str, Ptr::Owned(ref os_str) if **os_str == *OsStr::new("hi")));
+
+    // Slices of any other element type go through `owned_ptr`.
+    let json = r#"{"points": [[1, 2], [3, 4]], "labels": [null, 7]}"#;
+    let shape: Shape = serde_json::from_str(json).unwrap();
+    println!("shape  = {:?}", shape);
+    assert!(matches!(shape.points, Ptr::Owned(ref points) if **points == [(1, 2), (3, 4)]));
+    assert!(matches!(shape.labels, Ptr::Owned(ref labels) if **labels == [None, Some(7)]));
+
+    // A sized type is always owned.
+    let result: Outer<u64, u32> = serde_json::from_str(r#"{"s": "1", "ptr": 2}"#).unwrap();
+    println!("result = {:?}", result);
+
+    // Values read through `display_from_str` can be written back, and
+    // reading the output again gives the same value.
+    let json = r#"{"s": "1234567890", "ptr": "round trip\n"}"#;
+    let result: Outer<u64, str> = serde_json::from_str(json).unwrap();
+    let serialized = serde_json::to_string(&result).unwrap();
+    let reread: Outer<u64, str> = serde_json::from_str(&serialized).unwrap();
+    assert_eq!(serde_json::to_string(&reread).unwrap(), serialized);
+    println!("serialized = {}", serialized);
+
+    // Containers of `FromStr + Display` values, round tripped the same way.
+    let json = r#"
+        {
+            "listen": "0.0.0.0",
+            "peers": ["10.0.0.1", "::1"],
+            "weights": {"10.0.0.1": "3", "10.0.0.2": "1"},
+            "blocked": "192.168.0.1, 192.168.0.2"
+        }
+    "#;
+    let config: Config<IpAddr, u8> = serde_json::from_str(json).unwrap();
+    println!("config = {:?}", config);
+    let serialized = serde_json::to_string(&config).unwrap();
+    let reread: Config<IpAddr, u8> = serde_json::from_str(&serialized).unwrap();
+    assert_eq!(serde_json::to_string(&reread).unwrap(), serialized);
+    println!("serialized = {}", serialized);
+
+    // Items that would not read back as themselves are not written as a
+    // comma-separated list.
+    for blocked in &[vec!["a,b"], vec![" a"], vec![""]] {
+        let config: Config<String, u8> = Config {
+            listen: None,
+            peers: Vec::new(),
+            weights: BTreeMap::new(),
+            blocked: blocked.iter().map(|item| item.to_string()).collect(),
+        };
+        let error = serde_json::to_string(&config).unwrap_err();
+        println!("error = {}", error);
+    }
+    let config: Config<String, u8> = Config {
+        listen: None,
+        peers: Vec::new(),
+        weights: BTreeMap::new(),
+        blocked: vec![String::new(), String::new()],
+    };
+    let serialized = serde_json::to_string(&config).unwrap();
+    let reread: Config<String, u8> = serde_json::from_str(&serialized).unwrap();
+    assert_eq!(reread.blocked, config.blocked);
+
+    let json = r#"{"listen": null, "peers": [], "weights": {}, "blocked": "10.0.0.300"}"#;
+    let error = serde_json::from_str::<Config<IpAddr, u8>>(json).unwrap_err();
+    println!("error = {}", error);
+    // Items of a comma-separated list give the same structured error.
+    match from_json::<Config<IpAddr, u8>>(json).unwrap_err() {
+        JsonError::FromStr(structured) => {
+            assert_eq!(
+                structured,
+                parse_from_str::<IpAddr>("10.0.0.300").unwrap_err()
+            )
+        }
+        JsonError::Json(error) => panic!("not a FromStr failure: {}", error),
+    }
+
+    // Parse failures name the input and the target type.
+    let json = r#"{"s": "12345abc", "ptr": "borrowed"}"#;
+    let error = serde_json::from_str::<Outer<u64, str>>(json).unwrap_err();
+    println!("error = {}", error);
+
+    // The same failure as a structured error, from `from_json`.
+    let structured = match from_json::<Outer<u64, str>>(json).unwrap_err() {
+        JsonError::FromStr(structured) => structured,
+        JsonError::Json(error) => panic!("not a FromStr failure: {}", error),
+    };
+    println!("error = {}", serde_json::to_string(&structured).unwrap());
+    assert_eq!(structured, parse_from_str::<u64>("12345abc").unwrap_err());
+
+    // Inputs with quotes and escapes are kept exactly, and errors that did
+    // not come from `FromStr` are not mistaken for one, even right after a
+    // `FromStr` failure that was recovered from.
+    let json = r#"{"s": "say \"hi\"\n", "ptr": "borrowed"}"#;
+    let error = from_json::<Outer<u64, str>>(json).unwrap_err();
+    assert!(matches!(error, JsonError::FromStr(ref e) if e.input == "say \"hi\"\n"));
+    let error = from_json::<Outer<u64, str>>(r#"{"s": 1}"#).unwrap_err();
+    assert!(matches!(error, JsonError::Json(_)));
+    let port: Port = serde_json::from_str(r#""http""#).unwrap();
+    println!("port   = {:?}", port);
+    assert!(matches!(port, Port::Name(ref name) if name == "http"));
+    let port: Port = serde_json::from_str(r#""8080""#).unwrap();
+    assert!(matches!(port, Port::Number(8080)));
+    let error = from_json::<(Port, u8)>(r#"["http", "x"]"#).unwrap_err();
+    assert!(matches!(error, JsonError::Json(_)));
+
+    let error = parse_from_str::<u64>(&"9".repeat(40)).unwrap_err();
+    println!("error = {}", serde_json::to_string(&error).unwrap());
 }
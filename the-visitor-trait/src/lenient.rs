@@ -0,0 +1,369 @@
+//! Lenient scalars
+//!
+//! Data converted from CSV tends to be stringly typed: booleans arrive as
+//! `"yes"`, numbers arrive quoted and padded with whitespace. The wrappers in
+//! this module accept those inputs and record which coercion was needed to
+//! read them. With `STRICT = true` they accept only exact inputs instead.
+//!
+//! All of them deserialize with `deserialize_any`, since a type hint such as
+//! `deserialize_bool` would let a self-describing format reject a string
+//! before the visitor sees it. They therefore only work with self-describing
+//! formats.
+
+use std::fmt;
+use std::marker::PhantomData;
+
+use serde::de::{self, Deserialize, Deserializer, Unexpected, Visitor};
+
+use crate::{PrimitiveInt, RangedIntVisitor};
+
+/// The conversion that was applied to the input to produce a lenient value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Coercion {
+    /// The input already had the expected type.
+    Exact,
+    /// The value was parsed from a string, e.g. `"42"`.
+    FromString,
+    /// The value was parsed from a string after trimming whitespace, e.g.
+    /// `" 42 "`.
+    FromTrimmedString,
+    /// A boolean was read from the number `0` or `1`.
+    FromNumber,
+    /// An integer was read from a float with no fractional part, e.g. `7.0`.
+    FromFloat,
+    /// A float was read from an integer.
+    FromInteger,
+}
+
+/// Trims whitespace from a string input and reports which coercion that
+/// amounts to.
+fn trim(value: &str) -> (&str, Coercion) {
+    let trimmed = value.trim();
+    if trimmed.len() == value.len() {
+        (trimmed, Coercion::FromString)
+    } else {
+        (trimmed, Coercion::FromTrimmedString)
+    }
+}
+
+/// A `bool` that may also be read from `0`/`1` or from strings such as
+/// `"true"`, `"yes"`, `"on"` and `"1"`, in any case.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LenientBool<const STRICT: bool = false> {
+    pub value: bool,
+    pub coercion: Coercion,
+}
+
+struct LenientBoolVisitor<const STRICT: bool>;
+
+impl<'de, const STRICT: bool> Visitor<'de> for LenientBoolVisitor<STRICT> {
+    type Value = LenientBool<STRICT>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        if STRICT {
+            formatter.write_str("a boolean")
+        } else {
+            formatter.write_str("a boolean, 0 or 1, or a string such as \"yes\" or \"no\"")
+        }
+    }
+
+    fn visit_bool<E>(self, value: bool) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(LenientBool {
+            value,
+            coercion: Coercion::Exact,
+        })
+    }
+
+    fn visit_i64<E>(self, value: i64) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        if STRICT {
+            return Err(E::invalid_type(Unexpected::Signed(value), &self));
+        }
+        match value {
+            0 | 1 => Ok(LenientBool {
+                value: value == 1,
+                coercion: Coercion::FromNumber,
+            }),
+            _ => Err(E::invalid_value(Unexpected::Signed(value), &self)),
+        }
+    }
+
+    fn visit_u64<E>(self, value: u64) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        if STRICT {
+            return Err(E::invalid_type(Unexpected::Unsigned(value), &self));
+        }
+        match value {
+            0 | 1 => Ok(LenientBool {
+                value: value == 1,
+                coercion: Coercion::FromNumber,
+            }),
+            _ => Err(E::invalid_value(Unexpected::Unsigned(value), &self)),
+        }
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        if STRICT {
+            return Err(E::invalid_type(Unexpected::Str(value), &self));
+        }
+        let (trimmed, coercion) = trim(value);
+        match trimmed.to_ascii_lowercase().as_str() {
+            "true" | "yes" | "y" | "on" | "1" => Ok(LenientBool {
+                value: true,
+                coercion,
+            }),
+            "false" | "no" | "n" | "off" | "0" => Ok(LenientBool {
+                value: false,
+                coercion,
+            }),
+            _ => Err(E::invalid_value(Unexpected::Str(value), &self)),
+        }
+    }
+}
+
+impl<'de, const STRICT: bool> Deserialize<'de> for LenientBool<STRICT> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(LenientBoolVisitor::<STRICT>)
+    }
+}
+
+/// A primitive integer `T` that may also be read from a quoted, possibly
+/// padded, string or from a float with no fractional part.
+///
+/// The integer itself is converted by `RangedIntVisitor`, so the range of `T`
+/// is enforced the same way as for `RangedInt`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LenientInt<T, const STRICT: bool = false> {
+    pub value: T,
+    pub coercion: Coercion,
+}
+
+struct LenientIntVisitor<T, const STRICT: bool>(PhantomData<T>);
+
+impl<T: PrimitiveInt, const STRICT: bool> LenientIntVisitor<T, STRICT> {
+    /// The visitor that performs the actual conversion. The range is
+    /// clamped to the range of `T`.
+    fn inner() -> RangedIntVisitor<{ i128::MIN }, { i128::MAX }, T> {
+        RangedIntVisitor::new()
+    }
+
+    fn exact<E>(value: Result<T, E>) -> Result<LenientInt<T, STRICT>, E> {
+        value.map(|value| LenientInt {
+            value,
+            coercion: Coercion::Exact,
+        })
+    }
+}
+
+impl<'de, T: PrimitiveInt, const STRICT: bool> Visitor<'de> for LenientIntVisitor<T, STRICT> {
+    type Value = LenientInt<T, STRICT>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        Self::inner().expecting(formatter)?;
+        if !STRICT {
+            formatter.write_str(", or a string holding one")?;
+        }
+        Ok(())
+    }
+
+    fn visit_i64<E>(self, value: i64) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Self::exact(Self::inner().visit_i64(value))
+    }
+
+    fn visit_i128<E>(self, value: i128) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Self::exact(Self::inner().visit_i128(value))
+    }
+
+    fn visit_u64<E>(self, value: u64) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Self::exact(Self::inner().visit_u64(value))
+    }
+
+    fn visit_u128<E>(self, value: u128) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Self::exact(Self::inner().visit_u128(value))
+    }
+
+    fn visit_f64<E>(self, value: f64) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        if STRICT {
+            return Err(E::invalid_type(Unexpected::Float(value), &self));
+        }
+        Ok(LenientInt {
+            value: Self::inner().visit_f64(value)?,
+            coercion: Coercion::FromFloat,
+        })
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        if STRICT {
+            return Err(E::invalid_type(Unexpected::Str(value), &self));
+        }
+        let (trimmed, coercion) = trim(value);
+        Ok(LenientInt {
+            value: Self::inner().visit_str(trimmed)?,
+            coercion,
+        })
+    }
+}
+
+impl<'de, T: PrimitiveInt, const STRICT: bool> Deserialize<'de> for LenientInt<T, STRICT> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(LenientIntVisitor::<T, STRICT>(PhantomData))
+    }
+}
+
+/// A primitive float type that `LenientFloat` is able to produce.
+pub trait PrimitiveFloat: Sized {
+    /// Converts from a finite `f64`, rounding to the nearest value for
+    /// `f32`. Returns `None` if `value` is out of the range of `Self`.
+    fn from_f64(value: f64) -> Option<Self>;
+}
+
+impl PrimitiveFloat for f32 {
+    fn from_f64(value: f64) -> Option<Self> {
+        let rounded = value as f32;
+        if rounded.is_finite() {
+            Some(rounded)
+        } else {
+            None
+        }
+    }
+}
+
+impl PrimitiveFloat for f64 {
+    fn from_f64(value: f64) -> Option<Self> {
+        Some(value)
+    }
+}
+
+/// A primitive float `T` that may also be read from an integer or from a
+/// quoted, possibly padded, string. Strings must hold a finite number, so
+/// `"NaN"` and `"inf"` are rejected, and so is any value, number or string,
+/// out of the range of `T`, such as `1e300` for `f32`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LenientFloat<T, const STRICT: bool = false> {
+    pub value: T,
+    pub coercion: Coercion,
+}
+
+struct LenientFloatVisitor<T, const STRICT: bool>(PhantomData<T>);
+
+impl<'de, T: PrimitiveFloat, const STRICT: bool> Visitor<'de> for LenientFloatVisitor<T, STRICT> {
+    type Value = LenientFloat<T, STRICT>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        if STRICT {
+            formatter.write_str("a float")
+        } else {
+            formatter.write_str("a number, or a string holding one")
+        }
+    }
+
+    fn visit_i64<E>(self, value: i64) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        if STRICT {
+            return Err(E::invalid_type(Unexpected::Signed(value), &self));
+        }
+        match T::from_f64(value as f64) {
+            Some(value) => Ok(LenientFloat {
+                value,
+                coercion: Coercion::FromInteger,
+            }),
+            None => Err(E::invalid_value(Unexpected::Signed(value), &self)),
+        }
+    }
+
+    fn visit_u64<E>(self, value: u64) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        if STRICT {
+            return Err(E::invalid_type(Unexpected::Unsigned(value), &self));
+        }
+        match T::from_f64(value as f64) {
+            Some(value) => Ok(LenientFloat {
+                value,
+                coercion: Coercion::FromInteger,
+            }),
+            None => Err(E::invalid_value(Unexpected::Unsigned(value), &self)),
+        }
+    }
+
+    fn visit_f64<E>(self, value: f64) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        match T::from_f64(value) {
+            Some(value) => Ok(LenientFloat {
+                value,
+                coercion: Coercion::Exact,
+            }),
+            None => Err(E::invalid_value(Unexpected::Float(value), &self)),
+        }
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        if STRICT {
+            return Err(E::invalid_type(Unexpected::Str(value), &self));
+        }
+        let (trimmed, coercion) = trim(value);
+        match trimmed
+            .parse::<f64>()
+            .ok()
+            .filter(|parsed| parsed.is_finite())
+            .and_then(T::from_f64)
+        {
+            Some(parsed) => Ok(LenientFloat {
+                value: parsed,
+                coercion,
+            }),
+            None => Err(E::invalid_value(Unexpected::Str(value), &self)),
+        }
+    }
+}
+
+impl<'de, T: PrimitiveFloat, const STRICT: bool> Deserialize<'de> for LenientFloat<T, STRICT> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(LenientFloatVisitor::<T, STRICT>(PhantomData))
+    }
+}
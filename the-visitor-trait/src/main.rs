@@ -4,52 +4,423 @@
 //! `Deserializer`. The `Deserializer` then calls a method on the `Visitor` in
 //! order to construct the desired type.
 
+mod lenient;
+
+use std::convert::TryFrom;
 use std::fmt;
+use std::marker::PhantomData;
+
+use serde::de::{self, Deserialize, Deserializer, Unexpected, Visitor};
+use serde::ser::{Serialize, Serializer};
+
+use lenient::{LenientBool, LenientFloat, LenientInt};
+
+/// A primitive integer type that `RangedIntVisitor` is able to produce.
+trait PrimitiveInt: Sized + TryFrom<i128> {
+    /// The smallest value of the type, widened to `i128`.
+    const MIN: i128;
+
+    /// The largest value of the type, widened to `i128`. Saturates at
+    /// `i128::MAX` for `u128`.
+    const MAX: i128;
+
+    /// Calls the `deserialize_*` method of the `Deserializer` that hints at
+    /// this type, e.g. `deserialize_u16` for `u16`.
+    fn deserialize_primitive<'de, D, V>(deserializer: D, visitor: V) -> Result<V::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+        V: Visitor<'de>;
+}
 
-use serde::de::{self, Visitor};
+macro_rules! primitive_int {
+    ($($ty:ident => $method:ident,)*) => {
+        $(
+            impl PrimitiveInt for $ty {
+                const MIN: i128 = $ty::MIN as i128;
+                const MAX: i128 = if $ty::MAX as u128 > i128::MAX as u128 {
+                    i128::MAX
+                } else {
+                    $ty::MAX as i128
+                };
+
+                fn deserialize_primitive<'de, D, V>(
+                    deserializer: D,
+                    visitor: V,
+                ) -> Result<V::Value, D::Error>
+                where
+                    D: Deserializer<'de>,
+                    V: Visitor<'de>,
+                {
+                    deserializer.$method(visitor)
+                }
+            }
+        )*
+    };
+}
+
+primitive_int! {
+    i8 => deserialize_i8,
+    i16 => deserialize_i16,
+    i32 => deserialize_i32,
+    i64 => deserialize_i64,
+    i128 => deserialize_i128,
+    u8 => deserialize_u8,
+    u16 => deserialize_u16,
+    u32 => deserialize_u32,
+    u64 => deserialize_u64,
+    u128 => deserialize_u128,
+}
+
+/// A `Visitor` that is able to deserialize a primitive integer `T` in the
+/// range `MIN..=MAX` from a variety of types.
+///
+/// Every integer width is accepted as long as the value is in range. Floats
+/// are accepted only when they have no fractional part, and strings only
+/// when they parse as an integer. The range is additionally clamped to the
+/// range of `T`, so `RangedIntVisitor<0, 1000, u8>` accepts `0..=255`.
+struct RangedIntVisitor<const MIN: i128, const MAX: i128, T>(PhantomData<T>);
 
 /// A `Visitor` that is able to deserialize a primitive `i32` from a variety
 /// of types.
-#[allow(dead_code)]
-struct I32Vistor;
+type I32Vistor = RangedIntVisitor<{ i32::MIN as i128 }, { i32::MAX as i128 }, i32>;
+
+impl<const MIN: i128, const MAX: i128, T: PrimitiveInt> RangedIntVisitor<MIN, MAX, T> {
+    fn new() -> Self {
+        RangedIntVisitor(PhantomData)
+    }
 
-impl<'de> Visitor<'de> for I32Vistor {
-    type Value = i32;
+    /// The smallest value that is accepted.
+    fn low() -> i128 {
+        MIN.max(T::MIN)
+    }
+
+    /// The largest value that is accepted.
+    fn high() -> i128 {
+        MAX.min(T::MAX)
+    }
+
+    /// Every integer ends up here after being widened to `i128`.
+    fn in_range<E>(self, value: i128) -> Result<T, E>
+    where
+        E: de::Error,
+    {
+        if value >= Self::low() && value <= Self::high() {
+            if let Ok(value) = T::try_from(value) {
+                return Ok(value);
+            }
+        }
 
-    /// Formats a message stating that this `I32Visitor` expects to recieve
-    /// an `i32`.
+        // Describes the value with the narrowest `Unexpected` that fits it.
+        // `Unexpected` has no 128-bit variants.
+        if let Ok(value) = i64::try_from(value) {
+            Err(E::invalid_value(Unexpected::Signed(value), &self))
+        } else if let Ok(value) = u64::try_from(value) {
+            Err(E::invalid_value(Unexpected::Unsigned(value), &self))
+        } else {
+            Err(E::invalid_value(
+                Unexpected::Other(&format!("integer `{}`", value)),
+                &self,
+            ))
+        }
+    }
+}
+
+impl<'de, const MIN: i128, const MAX: i128, T: PrimitiveInt> Visitor<'de>
+    for RangedIntVisitor<MIN, MAX, T>
+{
+    type Value = T;
+
+    /// Formats a message stating the range of integers this visitor expects
+    /// to recieve.
     fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        // Writes the string to the formatter.
-        formatter.write_str("an integer between -2^32 and 2^31")
+        write!(
+            formatter,
+            "an integer between {} and {}",
+            Self::low(),
+            Self::high()
+        )
     }
 
     fn visit_i8<E>(self, value: i8) -> Result<Self::Value, E>
     where
         E: de::Error,
     {
-        Ok(i32::from(value))
+        self.in_range(i128::from(value))
+    }
+
+    fn visit_i16<E>(self, value: i16) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        self.in_range(i128::from(value))
     }
 
     fn visit_i32<E>(self, value: i32) -> Result<Self::Value, E>
     where
         E: de::Error,
     {
-        Ok(i32::from(value))
+        self.in_range(i128::from(value))
     }
 
     fn visit_i64<E>(self, value: i64) -> Result<Self::Value, E>
     where
         E: de::Error,
     {
-        use std::i32;
-        if value >= i64::from(i32::MIN) && value <= i64::from(i32::MAX) {
-            Ok(value as i32)
+        self.in_range(i128::from(value))
+    }
+
+    fn visit_i128<E>(self, value: i128) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        self.in_range(value)
+    }
+
+    fn visit_u8<E>(self, value: u8) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        self.in_range(i128::from(value))
+    }
+
+    fn visit_u16<E>(self, value: u16) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        self.in_range(i128::from(value))
+    }
+
+    fn visit_u32<E>(self, value: u32) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        self.in_range(i128::from(value))
+    }
+
+    fn visit_u64<E>(self, value: u64) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        self.in_range(i128::from(value))
+    }
+
+    fn visit_u128<E>(self, value: u128) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        match i128::try_from(value) {
+            Ok(value) => self.in_range(value),
+            Err(_) => Err(E::invalid_value(
+                Unexpected::Other(&format!("integer `{}`", value)),
+                &self,
+            )),
+        }
+    }
+
+    fn visit_f32<E>(self, value: f32) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        self.visit_f64(f64::from(value))
+    }
+
+    /// Accepts a float only if it is a whole number in range. `NaN` and the
+    /// infinities fail the range check.
+    fn visit_f64<E>(self, value: f64) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        let in_range = value >= Self::low() as f64 && value <= Self::high() as f64;
+        if in_range && value.fract() == 0.0 {
+            self.in_range(value as i128)
         } else {
-            Err(E::custom(format!("i32 out of range: {}", value)))
+            Err(E::invalid_value(Unexpected::Float(value), &self))
+        }
+    }
+
+    /// Accepts a string only if it parses as an integer. The parsed value
+    /// goes through the same range check as any other integer, so `"1e3"` is
+    /// rejected as not an integer while `"5000000000"` is rejected as out of
+    /// range.
+    fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        match value.parse::<i128>() {
+            Ok(parsed) => self.in_range(parsed),
+            Err(_) => Err(E::invalid_value(Unexpected::Str(value), &self)),
         }
     }
 }
 
+/// An integer of type `T` that only deserializes when it lies in
+/// `MIN..=MAX`, so range validation happens at the boundary instead of in
+/// every consumer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct RangedInt<const MIN: i128, const MAX: i128, T>(T);
+
+impl<'de, const MIN: i128, const MAX: i128, T: PrimitiveInt> Deserialize<'de>
+    for RangedInt<MIN, MAX, T>
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        T::deserialize_primitive(deserializer, RangedIntVisitor::<MIN, MAX, T>::new())
+            .map(RangedInt)
+    }
+}
+
+/// An `i32` that is deserialized by driving `I32Vistor` through
+/// `deserialize_i32`.
+///
+/// The hint matters for formats that are not self-describing, which need to
+/// know how many bytes to read. Self-describing formats such as JSON already
+/// know what kind of value comes next and call the matching `visit_*` method
+/// regardless: `serde_json` calls `visit_u64` for `7`, `visit_i64` for `-7`
+/// and `visit_f64` for `7.0`, but rejects `"7"` since the hint asks for a
+/// number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct StrictI32(i32);
+
+impl<'de> Deserialize<'de> for StrictI32 {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer
+            .deserialize_i32(I32Vistor::new())
+            .map(StrictI32)
+    }
+}
+
+impl Serialize for StrictI32 {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_i32(self.0)
+    }
+}
+
+/// A TCP or UDP port. Port 0 is reserved and rejected.
+type Port = RangedInt<1, 65535, u16>;
+
+/// A whole percentage.
+type Percent = RangedInt<0, 100, u8>;
+
 fn main() {
-    println!("Hello, world!");
+    // Boundary values for `StrictI32`, read from JSON, with the value each
+    // should give or the start of the error it should fail with.
+    let inputs: [(&str, Result<i32, &str>); 9] = [
+        ("-2147483648", Ok(i32::MIN)),
+        ("2147483647", Ok(i32::MAX)),
+        ("-2147483649", Err("invalid value: integer `-2147483649`")),
+        ("2147483648", Err("invalid value: integer `2147483648`")),
+        ("-9223372036854775808", Err("invalid value: integer")),
+        ("18446744073709551615", Err("invalid value: integer")),
+        ("2147483647.0", Ok(i32::MAX)),
+        ("0.5", Err("invalid value: floating point `0.5`")),
+        ("\"1234\"", Err("invalid type: string")),
+    ];
+    for (json, expected) in &inputs {
+        let result: Result<StrictI32, _> = serde_json::from_str(json);
+        println!("StrictI32 {:>22} => {:?}", json, result);
+        match (result, expected) {
+            (Ok(value), Ok(expected)) => assert_eq!(value, StrictI32(*expected)),
+            (Err(err), Err(expected)) => {
+                assert!(err.to_string().starts_with(expected), "{}: {}", json, err)
+            }
+            (result, expected) => panic!("{}: got {:?}, expected {:?}", json, result, expected),
+        }
+    }
+    println!();
+
+    // Every value that deserializes serializes back to the same JSON.
+    for value in &[i32::MIN, -1, 0, 1, i32::MAX] {
+        let serialized = serde_json::to_string(&StrictI32(*value)).unwrap();
+        let deserialized: StrictI32 = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(deserialized, StrictI32(*value));
+        println!("StrictI32 {:>11} round trips as {}", value, serialized);
+    }
+    println!();
+
+    // `serde_json::Value` is itself a `Deserializer`. Driving the visitor
+    // with `deserialize_any` instead of the `deserialize_i32` hint lets it
+    // see strings as well as numbers.
+    let inputs = vec![
+        serde_json::json!(42),
+        serde_json::json!(7.0),
+        serde_json::json!("1234"),
+        serde_json::json!("12ab"),
+        serde_json::json!(true),
+    ];
+    for input in inputs {
+        let result = input.clone().deserialize_any(I32Vistor::new());
+        println!("any {:>6} => {:?}", input.to_string(), result);
+    }
+    println!();
+
+    // `RangedInt` passes a type hint such as `deserialize_u16` to the
+    // `Deserializer`. `serde_json` answers that hint with numbers only, so the
+    // quoted port is rejected before the visitor ever sees the string.
+    for json in &["8080", "\"443\"", "0", "70000"] {
+        let port: Result<Port, _> = serde_json::from_str(json);
+        println!("port    {:>7} => {:?}", json, port);
+    }
+    for json in &["0", "100", "100.0", "101", "-1"] {
+        let percent: Result<Percent, _> = serde_json::from_str(json);
+        println!("percent {:>7} => {:?}", json, percent);
+    }
+    println!();
+
+    // Stringly-typed values as they come out of a CSV conversion. Each one
+    // records the coercion that was needed to read it.
+    let json = r#"[true, "yes", " No ", 1, "0", "maybe"]"#;
+    for input in serde_json::from_str::<Vec<serde_json::Value>>(json).unwrap() {
+        let lenient = LenientBool::<false>::deserialize(&input)
+            .map(|lenient| (lenient.value, lenient.coercion));
+        let strict = LenientBool::<true>::deserialize(&input).map(|strict| strict.value);
+        println!(
+            "bool  {:>8} => {:?}, strict {:?}",
+            input.to_string(),
+            lenient,
+            strict
+        );
+    }
+
+    let json = r#"[42, "42", " 42 ", 42.0, "4.2", "300"]"#;
+    for input in serde_json::from_str::<Vec<serde_json::Value>>(json).unwrap() {
+        let lenient =
+            LenientInt::<u8>::deserialize(&input).map(|lenient| (lenient.value, lenient.coercion));
+        let strict = LenientInt::<u8, true>::deserialize(&input).map(|strict| strict.value);
+        println!(
+            "u8    {:>8} => {:?}, strict {:?}",
+            input.to_string(),
+            lenient,
+            strict
+        );
+    }
+
+    let json = r#"[2.5, 2, "2.5", "\t2.5", "NaN"]"#;
+    for input in serde_json::from_str::<Vec<serde_json::Value>>(json).unwrap() {
+        let lenient = LenientFloat::<f64>::deserialize(&input)
+            .map(|lenient| (lenient.value, lenient.coercion));
+        let strict = LenientFloat::<f64, true>::deserialize(&input).map(|strict| strict.value);
+        println!(
+            "f64   {:>8} => {:?}, strict {:?}",
+            input.to_string(),
+            lenient,
+            strict
+        );
+    }
+
+    // `f32` rejects what it cannot hold instead of rounding it to infinity.
+    let json = r#"[1.5, 1e300, "1e300", "-1e39", 3.4e38]"#;
+    for input in serde_json::from_str::<Vec<serde_json::Value>>(json).unwrap() {
+        let lenient = LenientFloat::<f32>::deserialize(&input).map(|lenient| lenient.value);
+        println!("f32   {:>8} => {:?}", input.to_string(), lenient);
+        let in_range = input.as_f64().is_some_and(|value| value.abs() < 1e39);
+        assert_eq!(lenient.is_ok(), in_range, "{}", input);
+    }
 }